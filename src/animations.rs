@@ -0,0 +1,229 @@
+use crate::orbs::OrbType;
+use crate::run_history::RunEnd;
+use crate::simulation::Event;
+use crate::spells::{Position, SpellType};
+
+use keyframe::{
+    functions::{EaseIn, EaseOut},
+    keyframes, AnimationSequence,
+};
+use keyframe_derive::CanTween;
+
+// Seconds every animation takes
+const BURST_DURATION: f64 = 0.3;
+const ORB_SLIDE_DURATION: f64 = 0.08;
+const FAIL_DURATION: f64 = 0.6;
+
+// How far the field moves while it shakes, in pixels of the reference window
+const SHAKE_STRENGTH: f32 = 12.0;
+
+#[derive(CanTween, Clone, Copy, Default)]
+pub struct Fade {
+    pub scale: f32,
+    pub alpha: f32,
+}
+
+/// A spell that was cast, it grows and fades out where it was cleared.
+pub struct Burst {
+    pub spell: SpellType,
+    pub position: Position,
+    fade: AnimationSequence<Fade>,
+}
+
+impl Burst {
+    pub fn fade(&self) -> Fade {
+        self.fade.now()
+    }
+}
+
+/// Effects drawn on top of a run. They only move with the time that passes on screen
+/// and never feed back into the simulation, so a run plays the same with or without them.
+pub struct RunAnimations {
+    bursts: Vec<Burst>,
+    // Distance of the sliding orbs from their slots, in slots
+    orb_slide: Option<AnimationSequence<f32>>,
+    first_sliding_orb: usize,
+    // Opacity of the red flash after a lost run, the field shakes while it lasts
+    fail: Option<AnimationSequence<f32>>,
+}
+
+impl RunAnimations {
+    pub fn new() -> Self {
+        Self {
+            bursts: Vec::new(),
+            orb_slide: None,
+            first_sliding_orb: 0,
+            fail: None,
+        }
+    }
+
+    pub fn advance(&mut self, seconds: f64) {
+        for burst in &mut self.bursts {
+            burst.fade.advance_by(seconds);
+        }
+        self.bursts.retain(|burst| !burst.fade.finished());
+
+        if let Some(slide) = &mut self.orb_slide {
+            slide.advance_by(seconds);
+        }
+        if let Some(fail) = &mut self.fail {
+            fail.advance_by(seconds);
+        }
+    }
+
+    /// Starts the animations of what happened in the run.
+    pub fn handle(&mut self, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::SpellCast {
+                    spell, position, ..
+                } => self.cast(spell.clone(), position.clone()),
+                // Running out of time isn't a lost run
+                Event::GameOver(RunEnd::TimeUp) => {}
+                Event::GameOver(_) => self.fail(),
+                _ => {}
+            }
+        }
+    }
+
+    fn cast(&mut self, spell: SpellType, position: Position) {
+        let fade = keyframes![
+            (
+                Fade {
+                    scale: 1.0,
+                    alpha: 1.0
+                },
+                0.0,
+                EaseOut
+            ),
+            (
+                Fade {
+                    scale: 1.8,
+                    alpha: 0.0
+                },
+                BURST_DURATION
+            )
+        ];
+        self.bursts.push(Burst {
+            spell,
+            position,
+            fade,
+        });
+    }
+
+    /// Has to be called before the press reaches the simulation, `buffer_len` is the number
+    /// of orbs in the buffer before it. The new orb slides into its slot, a full buffer
+    /// pushes out its oldest orb and every orb moves along.
+    pub fn orb_pressed(&mut self, orb: Option<OrbType>, buffer_len: usize) {
+        if !matches!(orb, Some(OrbType::Quas | OrbType::Wex | OrbType::Exort)) {
+            return;
+        }
+        self.first_sliding_orb = if buffer_len == 3 { 0 } else { buffer_len };
+        self.orb_slide = Some(keyframes![(1.0, 0.0, EaseOut), (0.0, ORB_SLIDE_DURATION)]);
+    }
+
+    fn fail(&mut self) {
+        self.fail = Some(keyframes![(0.5, 0.0, EaseIn), (0.0, FAIL_DURATION)]);
+    }
+
+    pub fn bursts(&self) -> &[Burst] {
+        &self.bursts
+    }
+
+    // How far the orb in `slot` still is from it, in slots to the right
+    pub fn orb_offset(&self, slot: usize) -> f32 {
+        match &self.orb_slide {
+            Some(slide) if slot >= self.first_sliding_orb => slide.now(),
+            _ => 0.0,
+        }
+    }
+
+    /// The game over screen waits until the flash is over.
+    pub fn is_failing(&self) -> bool {
+        self.fail.as_ref().is_some_and(|fail| !fail.finished())
+    }
+
+    pub fn flash_alpha(&self) -> f32 {
+        self.fail.as_ref().map_or(0.0, |fail| fail.now())
+    }
+
+    // Offset of the field in pixels of the reference window, it dies down with the flash
+    pub fn shake(&self) -> (f32, f32) {
+        match &self.fail {
+            Some(fail) if !fail.finished() => {
+                let time = fail.time() as f32;
+                let strength = SHAKE_STRENGTH * (1.0 - fail.progress() as f32);
+                (
+                    (time * 70.0).sin() * strength,
+                    (time * 90.0).cos() * strength,
+                )
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn test_bursts_fade_out() {
+        let mut animations = RunAnimations::new();
+        animations.handle(&[Event::SpellCast {
+            id: 0,
+            spell: SpellType::new("Tornado"),
+            position: Position { x: 10.0, y: 20.0 },
+            at: Duration::ZERO,
+        }]);
+        assert_eq!(animations.bursts()[0].fade().alpha, 1.0);
+
+        animations.advance(BURST_DURATION / 2.0);
+        let fade = animations.bursts()[0].fade();
+        assert!(fade.alpha > 0.0 && fade.alpha < 1.0);
+        assert!(fade.scale > 1.0);
+
+        animations.advance(BURST_DURATION);
+        assert!(animations.bursts().is_empty());
+    }
+
+    #[test]
+    fn test_orb_slide() {
+        let mut animations = RunAnimations::new();
+        assert_eq!(animations.orb_offset(0), 0.0);
+
+        animations.orb_pressed(Some(OrbType::Invoke), 1);
+        assert_eq!(animations.orb_offset(1), 0.0);
+
+        // Only the new orb moves into a buffer that isn't full
+        animations.orb_pressed(Some(OrbType::Wex), 1);
+        assert_eq!(animations.orb_offset(0), 0.0);
+        assert_eq!(animations.orb_offset(1), 1.0);
+
+        animations.orb_pressed(Some(OrbType::Quas), 3);
+        assert_eq!(animations.orb_offset(0), 1.0);
+
+        animations.advance(ORB_SLIDE_DURATION);
+        assert_eq!(animations.orb_offset(2), 0.0);
+    }
+
+    #[test]
+    fn test_fail() {
+        let mut animations = RunAnimations::new();
+        animations.handle(&[Event::GameOver(RunEnd::TimeUp)]);
+        assert!(!animations.is_failing());
+
+        animations.handle(&[Event::GameOver(RunEnd::SpellReachedBottom {
+            spell: SpellType::new("Tornado"),
+        })]);
+        assert!(animations.is_failing());
+        assert_eq!(animations.flash_alpha(), 0.5);
+
+        animations.advance(FAIL_DURATION);
+        assert!(!animations.is_failing());
+        assert_eq!(animations.flash_alpha(), 0.0);
+        assert_eq!(animations.shake(), (0.0, 0.0));
+    }
+}
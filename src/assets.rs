@@ -3,3 +3,25 @@ pub const QUAS: &[u8] = include_bytes!("../resources/quas.png");
 pub const WEX: &[u8] = include_bytes!("../resources/wex.png");
 pub const EXORT: &[u8] = include_bytes!("../resources/exort.png");
 pub const INVOKE: &[u8] = include_bytes!("../resources/invoke.png");
+
+/// Width and height of an embedded PNG, read from its header.
+pub fn png_size(bytes: &[u8]) -> (f32, f32) {
+    let dimension = |start: usize| {
+        let bytes = bytes[start..start + 4]
+            .try_into()
+            .expect("Embedded images are PNGs");
+        u32::from_be_bytes(bytes) as f32
+    };
+    (dimension(16), dimension(20))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_size() {
+        assert_eq!(png_size(BACKGROUND_IMAGE), (1024.0, 1024.0));
+        assert_eq!(png_size(QUAS), (77.0, 77.0));
+    }
+}
@@ -0,0 +1,404 @@
+use crate::orbs::OrbType;
+use crate::settings::Settings;
+use crate::spells::SpellType;
+
+use std::collections::HashMap;
+
+use ggez::{
+    audio::{SoundData, SoundSource, Source},
+    Context, GameResult,
+};
+use serde::{Deserialize, Serialize};
+
+// The sounds are synthesized when a run starts, nothing has to be shipped with the game
+const SAMPLE_RATE: u32 = 22050;
+
+pub const MAX_VOLUME: u32 = 100;
+
+/// Volumes in percent, stored as the `[audio]` table of settings.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: u32,
+    pub effects: u32,
+    // No music is played at 0
+    pub music: u32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 80,
+            effects: 100,
+            music: 50,
+        }
+    }
+}
+
+impl AudioSettings {
+    // Volumes as ggez wants them, 1 is the volume of the sound itself
+    pub fn effects_volume(&self) -> f32 {
+        Self::volume(self.master) * Self::volume(self.effects)
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        Self::volume(self.master) * Self::volume(self.music)
+    }
+
+    fn volume(percent: u32) -> f32 {
+        percent.min(MAX_VOLUME) as f32 / MAX_VOLUME as f32
+    }
+}
+
+/// Sound effects and music of a run.
+pub struct Audio {
+    settings: AudioSettings,
+    // Quas, Wex and Exort
+    orbs: [Source; 3],
+    invoke: Source,
+    casts: HashMap<SpellType, Source>,
+    game_over: Source,
+    music: Option<Source>,
+}
+
+impl Audio {
+    pub fn new(ctx: &Context, settings: &Settings) -> GameResult<Self> {
+        let orbs = [
+            Self::source(ctx, &orb_sound('Q'))?,
+            Self::source(ctx, &orb_sound('W'))?,
+            Self::source(ctx, &orb_sound('E'))?,
+        ];
+        let invoke = Self::source(ctx, &invoke_sound())?;
+
+        let mut casts = HashMap::new();
+        for spell in settings.spell_book.spells() {
+            casts.insert(
+                spell.id.clone(),
+                Self::source(ctx, &cast_sound(&spell.cast()))?,
+            );
+        }
+
+        let game_over = Self::source(ctx, &game_over_sound())?;
+
+        let mut audio = Self {
+            settings: settings.audio,
+            orbs,
+            invoke,
+            casts,
+            game_over,
+            music: None,
+        };
+        audio.apply_settings(ctx, settings)?;
+        Ok(audio)
+    }
+
+    fn source(ctx: &Context, samples: &[f32]) -> GameResult<Source> {
+        Source::from_data(ctx, SoundData::from(wav(samples)))
+    }
+
+    /// Music starts or stops when its volume is turned up from or down to 0.
+    pub fn apply_settings(&mut self, ctx: &Context, settings: &Settings) -> GameResult {
+        self.settings = settings.audio;
+
+        let volume = self.settings.music_volume();
+        if volume <= 0.0 {
+            self.music = None;
+            return Ok(());
+        }
+
+        if self.music.is_none() {
+            let mut music = Self::source(ctx, &music())?;
+            music.set_repeat(true);
+            music.play(ctx)?;
+            self.music = Some(music);
+        }
+        if let Some(music) = &mut self.music {
+            music.set_volume(volume);
+        }
+        Ok(())
+    }
+
+    pub fn play_orb(&mut self, ctx: &Context, orb: OrbType) {
+        let source = match orb {
+            OrbType::Quas => &mut self.orbs[0],
+            OrbType::Wex => &mut self.orbs[1],
+            OrbType::Exort => &mut self.orbs[2],
+            OrbType::Invoke => &mut self.invoke,
+            // A slot only makes a sound when it casts
+            OrbType::FirstSlot | OrbType::SecondSlot => return,
+        };
+        Self::play(ctx, source, self.settings.effects_volume());
+    }
+
+    pub fn play_cast(&mut self, ctx: &Context, spell: &SpellType) {
+        let volume = self.settings.effects_volume();
+        if let Some(source) = self.casts.get_mut(spell) {
+            Self::play(ctx, source, volume);
+        }
+    }
+
+    pub fn play_game_over(&mut self, ctx: &Context) {
+        self.music = None;
+        let volume = self.settings.effects_volume();
+        Self::play(ctx, &mut self.game_over, volume);
+    }
+
+    pub fn pause_music(&self) {
+        if let Some(music) = &self.music {
+            music.pause();
+        }
+    }
+
+    pub fn resume_music(&self) {
+        if let Some(music) = &self.music {
+            music.resume();
+        }
+    }
+
+    // Detached, so quick presses overlap instead of cutting each other off.
+    // The volume belongs to the sink that is detached, it has to be set every time
+    fn play(ctx: &Context, source: &mut Source, volume: f32) {
+        if volume <= 0.0 {
+            return;
+        }
+        source.set_volume(volume);
+        if let Err(e) = source.play_detached(ctx) {
+            println!("Could not play sound: {}", e);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Wave {
+    Sine,
+    Triangle,
+    Square,
+}
+
+impl Wave {
+    // `phase` counts whole periods
+    fn sample(self, phase: f32) -> f32 {
+        let phase = phase.fract();
+        match self {
+            Wave::Sine => (phase * std::f32::consts::TAU).sin(),
+            Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Wave::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+        }
+    }
+}
+
+// Every orb has its own pitch and timbre, a spell plays the orbs of its recipe
+fn orb_voice(orb: char) -> (Wave, f32) {
+    match orb {
+        'Q' => (Wave::Sine, 392.0),
+        'W' => (Wave::Triangle, 523.25),
+        _ => (Wave::Square, 659.25),
+    }
+}
+
+/// Adds a note that fades in quickly and dies out over `duration` seconds.
+fn add_note(
+    samples: &mut Vec<f32>,
+    wave: Wave,
+    frequency: f32,
+    start: f32,
+    duration: f32,
+    amplitude: f32,
+) {
+    let first = (start * SAMPLE_RATE as f32) as usize;
+    let length = (duration * SAMPLE_RATE as f32) as usize;
+    if samples.len() < first + length {
+        samples.resize(first + length, 0.0);
+    }
+
+    let attack = 0.005 * SAMPLE_RATE as f32;
+    for i in 0..length {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let envelope = (i as f32 / attack).min(1.0) * (1.0 - i as f32 / length as f32).powi(2);
+        samples[first + i] += wave.sample(t * frequency) * envelope * amplitude;
+    }
+}
+
+// The square wave sounds a lot louder than the others
+fn orb_amplitude(wave: Wave) -> f32 {
+    match wave {
+        Wave::Square => 0.2,
+        Wave::Sine | Wave::Triangle => 0.5,
+    }
+}
+
+fn orb_sound(orb: char) -> Vec<f32> {
+    let mut samples = Vec::new();
+    let (wave, frequency) = orb_voice(orb);
+    add_note(
+        &mut samples,
+        wave,
+        frequency,
+        0.0,
+        0.12,
+        orb_amplitude(wave),
+    );
+    samples
+}
+
+fn invoke_sound() -> Vec<f32> {
+    let mut samples = Vec::new();
+    add_note(&mut samples, Wave::Triangle, 783.99, 0.0, 0.08, 0.4);
+    add_note(&mut samples, Wave::Triangle, 1046.5, 0.04, 0.14, 0.4);
+    samples
+}
+
+// Recipes are unique, so every spell sounds different
+fn cast_sound(recipe: &[char]) -> Vec<f32> {
+    let mut samples = Vec::new();
+    for (index, orb) in recipe.iter().enumerate() {
+        let (wave, frequency) = orb_voice(*orb);
+        let amplitude = orb_amplitude(wave) * 0.7;
+        add_note(
+            &mut samples,
+            wave,
+            frequency * 2.0,
+            index as f32 * 0.06,
+            0.18,
+            amplitude,
+        );
+    }
+    samples
+}
+
+fn game_over_sound() -> Vec<f32> {
+    let mut samples = Vec::new();
+    for (index, frequency) in [392.0, 311.13, 261.63, 196.0].into_iter().enumerate() {
+        let duration = if index == 3 { 0.8 } else { 0.2 };
+        add_note(
+            &mut samples,
+            Wave::Square,
+            frequency,
+            index as f32 * 0.18,
+            duration,
+            0.2,
+        );
+    }
+    samples
+}
+
+// Eight seconds of arpeggios over Am F C G that loop seamlessly
+fn music() -> Vec<f32> {
+    let chords = [
+        [220.0, 261.63, 329.63],
+        [174.61, 220.0, 261.63],
+        [261.63, 329.63, 392.0],
+        [196.0, 246.94, 293.66],
+    ];
+    let chord_length = 2.0;
+
+    let mut samples =
+        vec![0.0; (chords.len() as f32 * chord_length) as usize * SAMPLE_RATE as usize];
+    for (index, chord) in chords.iter().enumerate() {
+        let start = index as f32 * chord_length;
+        add_note(
+            &mut samples,
+            Wave::Sine,
+            chord[0] / 2.0,
+            start,
+            chord_length,
+            0.3,
+        );
+
+        for step in 0..8 {
+            let frequency = chord[[0, 1, 2, 1][step % 4]] * 2.0;
+            let note_start = start + step as f32 * chord_length / 8.0;
+            add_note(
+                &mut samples,
+                Wave::Triangle,
+                frequency,
+                note_start,
+                0.25,
+                0.12,
+            );
+        }
+    }
+    samples
+}
+
+/// 16 bit mono PCM, samples outside of -1 to 1 are clipped.
+fn wav(samples: &[f32]) -> Vec<u8> {
+    let data_size = samples.len() as u32 * 2;
+
+    let mut bytes = Vec::with_capacity(44 + data_size as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    // PCM with one channel
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spell_book::SpellBook;
+
+    #[test]
+    fn test_wav() {
+        let bytes = wav(&[0.0, 1.0, -2.0]);
+        assert_eq!(bytes.len(), 44 + 6);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 6);
+        assert_eq!(i16::from_le_bytes([bytes[46], bytes[47]]), i16::MAX);
+        assert_eq!(i16::from_le_bytes([bytes[48], bytes[49]]), -i16::MAX);
+    }
+
+    #[test]
+    fn test_spells_sound_different() {
+        let sounds: Vec<Vec<f32>> = SpellBook::default()
+            .spells()
+            .iter()
+            .map(|spell| cast_sound(&spell.cast()))
+            .collect();
+
+        for (index, sound) in sounds.iter().enumerate() {
+            assert!(!sound.is_empty());
+            assert!(sounds[index + 1..].iter().all(|other| other != sound));
+        }
+    }
+
+    #[test]
+    fn test_music_loops() {
+        // Notes that ring past the end would be cut off when the loop starts over
+        let samples = music();
+        assert_eq!(samples.len(), 8 * SAMPLE_RATE as usize);
+        assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+    }
+
+    #[test]
+    fn test_volumes() {
+        let settings = AudioSettings {
+            master: 50,
+            effects: 50,
+            music: 0,
+        };
+        assert_eq!(settings.effects_volume(), 0.25);
+        assert_eq!(settings.music_volume(), 0.0);
+    }
+}
@@ -0,0 +1,374 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use ggez::event::MouseButton;
+use ggez::input::keyboard::{KeyCode, KeyMods};
+
+// Every key ggez knows about, a key is written to settings.toml by its name here
+const KEYS: [KeyCode; 163] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Escape,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::F13,
+    KeyCode::F14,
+    KeyCode::F15,
+    KeyCode::F16,
+    KeyCode::F17,
+    KeyCode::F18,
+    KeyCode::F19,
+    KeyCode::F20,
+    KeyCode::F21,
+    KeyCode::F22,
+    KeyCode::F23,
+    KeyCode::F24,
+    KeyCode::Snapshot,
+    KeyCode::Scroll,
+    KeyCode::Pause,
+    KeyCode::Insert,
+    KeyCode::Home,
+    KeyCode::Delete,
+    KeyCode::End,
+    KeyCode::PageDown,
+    KeyCode::PageUp,
+    KeyCode::Left,
+    KeyCode::Up,
+    KeyCode::Right,
+    KeyCode::Down,
+    KeyCode::Back,
+    KeyCode::Return,
+    KeyCode::Space,
+    KeyCode::Compose,
+    KeyCode::Caret,
+    KeyCode::Numlock,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::NumpadAdd,
+    KeyCode::NumpadDivide,
+    KeyCode::NumpadDecimal,
+    KeyCode::NumpadComma,
+    KeyCode::NumpadEnter,
+    KeyCode::NumpadEquals,
+    KeyCode::NumpadMultiply,
+    KeyCode::NumpadSubtract,
+    KeyCode::AbntC1,
+    KeyCode::AbntC2,
+    KeyCode::Apostrophe,
+    KeyCode::Apps,
+    KeyCode::Asterisk,
+    KeyCode::At,
+    KeyCode::Ax,
+    KeyCode::Backslash,
+    KeyCode::Calculator,
+    KeyCode::Capital,
+    KeyCode::Colon,
+    KeyCode::Comma,
+    KeyCode::Convert,
+    KeyCode::Equals,
+    KeyCode::Grave,
+    KeyCode::Kana,
+    KeyCode::Kanji,
+    KeyCode::LAlt,
+    KeyCode::LBracket,
+    KeyCode::LControl,
+    KeyCode::LShift,
+    KeyCode::LWin,
+    KeyCode::Mail,
+    KeyCode::MediaSelect,
+    KeyCode::MediaStop,
+    KeyCode::Minus,
+    KeyCode::Mute,
+    KeyCode::MyComputer,
+    KeyCode::NavigateForward,
+    KeyCode::NavigateBackward,
+    KeyCode::NextTrack,
+    KeyCode::NoConvert,
+    KeyCode::OEM102,
+    KeyCode::Period,
+    KeyCode::PlayPause,
+    KeyCode::Plus,
+    KeyCode::Power,
+    KeyCode::PrevTrack,
+    KeyCode::RAlt,
+    KeyCode::RBracket,
+    KeyCode::RControl,
+    KeyCode::RShift,
+    KeyCode::RWin,
+    KeyCode::Semicolon,
+    KeyCode::Slash,
+    KeyCode::Sleep,
+    KeyCode::Stop,
+    KeyCode::Sysrq,
+    KeyCode::Tab,
+    KeyCode::Underline,
+    KeyCode::Unlabeled,
+    KeyCode::VolumeDown,
+    KeyCode::VolumeUp,
+    KeyCode::Wake,
+    KeyCode::WebBack,
+    KeyCode::WebFavorites,
+    KeyCode::WebForward,
+    KeyCode::WebHome,
+    KeyCode::WebRefresh,
+    KeyCode::WebSearch,
+    KeyCode::WebStop,
+    KeyCode::Yen,
+    KeyCode::Copy,
+    KeyCode::Paste,
+    KeyCode::Cut,
+];
+
+// Order the modifiers are written in, e.g. "Ctrl+Shift+Q"
+const MODIFIERS: [(KeyMods, &str); 4] = [
+    (KeyMods::CTRL, "Ctrl"),
+    (KeyMods::SHIFT, "Shift"),
+    (KeyMods::ALT, "Alt"),
+    (KeyMods::LOGO, "Logo"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// A key or mouse button that presses an orb, optionally only together with modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub input: Input,
+    pub mods: KeyMods,
+}
+
+impl Binding {
+    /// The binding a key press matches. A modifier key pressed on its own
+    /// already shows up in `mods`, so it doesn't count as a modifier of itself.
+    pub fn from_key(key: KeyCode, mods: KeyMods) -> Self {
+        let mods = match Self::modifier(key) {
+            Some(own) => mods - own,
+            None => mods,
+        };
+        Self {
+            input: Input::Key(key),
+            mods,
+        }
+    }
+
+    pub fn from_mouse(button: MouseButton, mods: KeyMods) -> Self {
+        Self {
+            input: Input::Mouse(button),
+            mods,
+        }
+    }
+
+    /// Parses names like "Q", "Space", "F1", "Numpad1", "Mouse4" or "Ctrl+Shift+Q".
+    pub fn parse(binding: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = binding.split('+').map(str::trim).collect();
+        let name = parts.pop().unwrap_or_default();
+
+        let mut mods = KeyMods::empty();
+        for part in parts {
+            let modifier = match part.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyMods::CTRL,
+                "shift" => KeyMods::SHIFT,
+                "alt" => KeyMods::ALT,
+                "logo" | "super" | "cmd" | "win" => KeyMods::LOGO,
+                _ => return Err(format!("\"{}\" is not a modifier", part)),
+            };
+            mods |= modifier;
+        }
+
+        let input = Self::parse_input(name)
+            .ok_or_else(|| format!("\"{}\" is not a key or mouse button", name))?;
+        Ok(match input {
+            Input::Key(key) => Self::from_key(key, mods),
+            Input::Mouse(button) => Self::from_mouse(button, mods),
+        })
+    }
+
+    fn parse_input(name: &str) -> Option<Input> {
+        let lowercase = name.to_lowercase();
+
+        if let Some(button) = lowercase.strip_prefix("mouse") {
+            let button = match button {
+                "left" | "1" => MouseButton::Left,
+                "right" | "2" => MouseButton::Right,
+                "middle" | "3" => MouseButton::Middle,
+                number => MouseButton::Other(number.parse().ok()?),
+            };
+            return Some(Input::Mouse(button));
+        }
+
+        // Digits and a few common names that differ from the ggez ones
+        let key = match lowercase.as_str() {
+            "enter" => Some(KeyCode::Return),
+            "backspace" => Some(KeyCode::Back),
+            digit if digit.len() == 1 && digit.chars().all(|c| c.is_ascii_digit()) => {
+                Self::find_key(&format!("key{}", digit))
+            }
+            name => Self::find_key(name),
+        };
+        key.map(Input::Key)
+    }
+
+    fn find_key(lowercase: &str) -> Option<KeyCode> {
+        KEYS.into_iter()
+            .find(|key| format!("{:?}", key).to_lowercase() == lowercase)
+    }
+
+    fn modifier(key: KeyCode) -> Option<KeyMods> {
+        match key {
+            KeyCode::LControl | KeyCode::RControl => Some(KeyMods::CTRL),
+            KeyCode::LShift | KeyCode::RShift => Some(KeyMods::SHIFT),
+            KeyCode::LAlt | KeyCode::RAlt => Some(KeyMods::ALT),
+            KeyCode::LWin | KeyCode::RWin => Some(KeyMods::LOGO),
+            _ => None,
+        }
+    }
+
+    // Holding Ctrl for a Ctrl+Q binding shouldn't count as a press of its own
+    pub fn is_modifier_key(key: KeyCode) -> bool {
+        Self::modifier(key).is_some()
+    }
+
+    // Escape always leaves the run, so it can't press an orb
+    pub fn is_bindable(&self) -> bool {
+        self.input != Input::Key(KeyCode::Escape)
+    }
+}
+
+// KeyMods doesn't implement Hash, its bits are hashed instead
+impl Hash for Binding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.input.hash(state);
+        self.mods.bits().hash(state);
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in MODIFIERS {
+            if self.mods.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+
+        match self.input {
+            Input::Key(key) => write!(f, "{:?}", key),
+            Input::Mouse(MouseButton::Left) => write!(f, "MouseLeft"),
+            Input::Mouse(MouseButton::Right) => write!(f, "MouseRight"),
+            Input::Mouse(MouseButton::Middle) => write!(f, "MouseMiddle"),
+            Input::Mouse(MouseButton::Other(number)) => write!(f, "Mouse{}", number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: KeyCode) -> Binding {
+        Binding::from_key(key, KeyMods::empty())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Binding::parse("Q"), Ok(key(KeyCode::Q)));
+        assert_eq!(Binding::parse("space"), Ok(key(KeyCode::Space)));
+        assert_eq!(Binding::parse("1"), Ok(key(KeyCode::Key1)));
+        assert_eq!(Binding::parse("Numpad1"), Ok(key(KeyCode::Numpad1)));
+        assert_eq!(
+            Binding::parse("Mouse4"),
+            Ok(Binding::from_mouse(MouseButton::Other(4), KeyMods::empty()))
+        );
+        assert_eq!(
+            Binding::parse("Mouse1"),
+            Ok(Binding::from_mouse(MouseButton::Left, KeyMods::empty()))
+        );
+        assert_eq!(
+            Binding::parse("Ctrl + Shift+F1"),
+            Ok(Binding::from_key(
+                KeyCode::F1,
+                KeyMods::CTRL | KeyMods::SHIFT
+            ))
+        );
+
+        assert!(Binding::parse("").is_err());
+        assert!(Binding::parse("NotAKey").is_err());
+        assert!(Binding::parse("Hyper+Q").is_err());
+        assert!(Binding::parse("MouseSide").is_err());
+    }
+
+    #[test]
+    fn test_every_key_roundtrips() {
+        for code in KEYS {
+            let binding = key(code);
+            assert_eq!(Binding::parse(&binding.to_string()), Ok(binding));
+        }
+
+        for binding in ["Ctrl+Alt+Q", "Shift+MouseRight", "MouseMiddle", "Mouse8"] {
+            assert_eq!(Binding::parse(binding).unwrap().to_string(), binding);
+        }
+    }
+
+    #[test]
+    fn test_modifier_key_on_its_own() {
+        let binding = Binding::from_key(KeyCode::LShift, KeyMods::SHIFT);
+        assert_eq!(binding, key(KeyCode::LShift));
+        assert_eq!(Binding::parse("LShift"), Ok(binding));
+        assert!(Binding::is_modifier_key(KeyCode::RControl));
+        assert!(!Binding::is_modifier_key(KeyCode::Q));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::settings::Settings;
 
-use ggez::graphics::{DrawParam, Drawable};
+use ggez::graphics::{DrawParam, Drawable, Transform};
 use ggez::{
     glam::*,
     graphics::{self, Color, Rect},
@@ -89,4 +89,20 @@ impl MenuButton {
             draw_param,
         })
     }
+
+    /// Draws the button moved by `offset` and faded to `alpha`, e.g. while it eases in.
+    pub fn draw_moved(&self, canvas: &mut graphics::Canvas, offset: Vec2, alpha: f32) {
+        let moved = |mut param: DrawParam| {
+            if let Transform::Values { dest, .. } = &mut param.transform {
+                dest.x += offset.x;
+                dest.y += offset.y;
+            }
+            param.color.a *= alpha;
+            param
+        };
+
+        canvas.draw(&self.background, moved(self.draw_param));
+        canvas.draw(&self.text, moved(self.text_draw_param));
+        canvas.draw(&self.hint, moved(self.hint_draw_param));
+    }
 }
@@ -0,0 +1,283 @@
+use crate::bindings::Binding;
+use crate::highscores::{GameMode, Highscores};
+use crate::settings::{Settings, TIME_LIMITS, WINDOW_HEIGHTS, WINDOW_WIDTHS};
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: invoke [OPTIONS]
+
+Options:
+    --mode <normal|pro|daily|slots|graded|combos|time-attack>
+                               Start a run right away instead of the menu
+    --seed <NUMBER>            Seed of the run, needs any --mode but daily
+    --settings <FILE>          Use another settings file instead of settings.toml
+    --window <WIDTHxHEIGHT>    Window size for this session, e.g. 1280x720
+    --quas <BINDING>           Binding of Quas for this session, e.g. Q or Ctrl+Space
+    --wex <BINDING>            Binding of Wex for this session
+    --exort <BINDING>          Binding of Exort for this session
+    --invoke <BINDING>         Binding of Invoke for this session
+    --first-slot <BINDING>     Binding of the first spell slot for this session
+    --second-slot <BINDING>    Binding of the second spell slot for this session
+    --highscores               Print the stored highscores and exit
+    --help                     Print this message and exit";
+
+/// What the game was asked to do on the command line.
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub mode: Option<GameMode>,
+    pub seed: Option<u64>,
+    pub settings_path: Option<PathBuf>,
+    pub window_size: Option<(f32, f32)>,
+    pub quas_key: Option<Binding>,
+    pub wex_key: Option<Binding>,
+    pub exort_key: Option<Binding>,
+    pub invoke_key: Option<Binding>,
+    pub first_slot_key: Option<Binding>,
+    pub second_slot_key: Option<Binding>,
+    pub highscores: bool,
+    pub help: bool,
+}
+
+impl Options {
+    /// Parses the arguments without the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Both "--mode pro" and "--mode=pro" work
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+
+            match name.as_str() {
+                "--mode" => options.mode = Some(Self::parse_mode(&value()?)?),
+                "--seed" => {
+                    let seed = value()?;
+                    let seed = seed
+                        .parse()
+                        .map_err(|_| format!("Seed \"{}\" is not a positive number", seed))?;
+                    options.seed = Some(seed);
+                }
+                "--settings" => options.settings_path = Some(PathBuf::from(value()?)),
+                "--window" => options.window_size = Some(Self::parse_window_size(&value()?)?),
+                "--quas" => options.quas_key = Some(Self::parse_binding(&value()?)?),
+                "--wex" => options.wex_key = Some(Self::parse_binding(&value()?)?),
+                "--exort" => options.exort_key = Some(Self::parse_binding(&value()?)?),
+                "--invoke" => options.invoke_key = Some(Self::parse_binding(&value()?)?),
+                "--first-slot" => options.first_slot_key = Some(Self::parse_binding(&value()?)?),
+                "--second-slot" => options.second_slot_key = Some(Self::parse_binding(&value()?)?),
+                "--highscores" => options.highscores = true,
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("Unknown option {}", name)),
+            }
+        }
+
+        // The daily challenge has to be the same for everyone
+        match (options.mode, options.seed) {
+            (None, Some(_)) => Err(String::from("--seed needs a --mode other than daily")),
+            (Some(GameMode::Daily), Some(_)) => Err(String::from(
+                "The daily challenge always uses the seed of the day",
+            )),
+            _ => Ok(options),
+        }
+    }
+
+    fn parse_mode(mode: &str) -> Result<GameMode, String> {
+        match mode.to_lowercase().as_str() {
+            "normal" => Ok(GameMode::Normal),
+            "pro" => Ok(GameMode::Pro),
+            "daily" => Ok(GameMode::Daily),
+            "slots" => Ok(GameMode::Slots),
+            "graded" => Ok(GameMode::GradedPro),
+            "combos" => Ok(GameMode::Combos),
+            "time-attack" => Ok(GameMode::TimeAttack),
+            _ => Err(format!(
+                "Unknown mode \"{}\", expected normal, pro, daily, slots, graded, combos or time-attack",
+                mode
+            )),
+        }
+    }
+
+    fn parse_window_size(size: &str) -> Result<(f32, f32), String> {
+        let invalid = || format!("Window size \"{}\" should look like 1280x720", size);
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let width: f32 = width.trim().parse().map_err(|_| invalid())?;
+        let height: f32 = height.trim().parse().map_err(|_| invalid())?;
+
+        if !WINDOW_WIDTHS.contains(&width) || !WINDOW_HEIGHTS.contains(&height) {
+            return Err(format!(
+                "Window size {}x{} is not between {}x{} and {}x{}",
+                width,
+                height,
+                WINDOW_WIDTHS.start(),
+                WINDOW_HEIGHTS.start(),
+                WINDOW_WIDTHS.end(),
+                WINDOW_HEIGHTS.end()
+            ));
+        }
+        Ok((width, height))
+    }
+
+    fn parse_binding(binding: &str) -> Result<Binding, String> {
+        let binding = Binding::parse(binding)?;
+        if !binding.is_bindable() {
+            return Err(format!("{} can't be bound", binding));
+        }
+        Ok(binding)
+    }
+
+    /// Applies the overrides for this session, saving the settings leaves them out.
+    pub fn apply(&self, settings_in_file: &Settings) -> Result<Settings, String> {
+        let mut settings = match self.window_size {
+            Some((width, height)) => settings_in_file.with_window_size(width, height),
+            None => settings_in_file.clone(),
+        };
+
+        settings.quas_key = self.quas_key.unwrap_or(settings.quas_key);
+        settings.wex_key = self.wex_key.unwrap_or(settings.wex_key);
+        settings.exort_key = self.exort_key.unwrap_or(settings.exort_key);
+        settings.invoke_key = self.invoke_key.unwrap_or(settings.invoke_key);
+        settings.first_slot_key = self.first_slot_key.unwrap_or(settings.first_slot_key);
+        settings.second_slot_key = self.second_slot_key.unwrap_or(settings.second_slot_key);
+
+        match settings.binding_conflict() {
+            Some(conflict) => Err(conflict),
+            None => Ok(settings.overriding(settings_in_file)),
+        }
+    }
+}
+
+/// The stored highscores of every mode as plain text.
+pub fn highscore_listing(highscores: &Highscores) -> String {
+    let mut listing = String::new();
+
+    let mut tables: Vec<(GameMode, Option<u64>, String)> = [
+        (GameMode::Normal, "Normal"),
+        (GameMode::Pro, "Pro"),
+        (GameMode::Daily, "Daily challenge"),
+        (GameMode::Slots, "Slot mode"),
+        (GameMode::GradedPro, "Graded Pro"),
+        (GameMode::Combos, "Combo drill"),
+    ]
+    .into_iter()
+    .map(|(mode, title)| (mode, None, String::from(title)))
+    .collect();
+    // Time attack runs are only compared to runs of the same length
+    tables.extend(TIME_LIMITS.into_iter().map(|time_limit| {
+        let title = format!("Time attack {}s", time_limit);
+        (GameMode::TimeAttack, Some(time_limit.into()), title)
+    }));
+
+    for (mode, time_limit, title) in tables {
+        let _ = writeln!(listing, "{}", title);
+
+        let entries = highscores.entries(mode, time_limit);
+        if entries.is_empty() {
+            let _ = writeln!(listing, "  No runs yet");
+        }
+        for (position, entry) in entries.iter().enumerate() {
+            // Spells per minute for time attack runs
+            let speed = entry.speed.or_else(|| entry.spells_per_minute());
+            let _ = writeln!(
+                listing,
+                "  {:>2}. {:>5}  speed {:>3.0}  {}  {}",
+                position + 1,
+                entry.score,
+                speed.unwrap_or_default(),
+                entry.date,
+                entry.keybindings
+            );
+        }
+    }
+
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highscores::HighscoreEntry;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(""), Ok(Options::default()));
+
+        let options =
+            parse("--mode pro --seed=42 --settings other.toml --window 1280x720").unwrap();
+        assert_eq!(options.mode, Some(GameMode::Pro));
+        assert_eq!(parse("--mode slots").unwrap().mode, Some(GameMode::Slots));
+        assert_eq!(
+            parse("--mode time-attack").unwrap().mode,
+            Some(GameMode::TimeAttack)
+        );
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.settings_path, Some(PathBuf::from("other.toml")));
+        assert_eq!(options.window_size, Some((1280.0, 720.0)));
+
+        let options =
+            parse("--quas Ctrl+Space --invoke Mouse4 --second-slot G --highscores").unwrap();
+        assert_eq!(options.quas_key, Binding::parse("Ctrl+Space").ok());
+        assert_eq!(options.invoke_key, Binding::parse("Mouse4").ok());
+        assert_eq!(options.second_slot_key, Binding::parse("G").ok());
+        assert!(options.highscores);
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(parse("--mode").is_err());
+        assert!(parse("--mode hard").is_err());
+        assert!(parse("--seed 42").is_err());
+        assert!(parse("--mode daily --seed 42").is_err());
+        assert!(parse("--mode normal --seed -1").is_err());
+        assert!(parse("--window 1280").is_err());
+        assert!(parse("--window 10x10").is_err());
+        assert!(parse("--wex Escape").is_err());
+        assert!(parse("--wex NotAKey").is_err());
+        assert!(parse("--fullscreen").is_err());
+    }
+
+    #[test]
+    fn test_highscore_listing() {
+        let mut highscores = Highscores::default();
+        highscores.insert(
+            GameMode::Pro,
+            HighscoreEntry {
+                date: String::from("2024-01-01"),
+                score: 42,
+                speed: Some(120.0),
+                time_limit: None,
+                keybindings: String::from("Q W E R"),
+            },
+        );
+        highscores.insert(
+            GameMode::TimeAttack,
+            HighscoreEntry {
+                date: String::from("2024-01-01"),
+                score: 25,
+                speed: None,
+                time_limit: Some(30),
+                keybindings: String::from("Q W E R"),
+            },
+        );
+
+        let listing = highscore_listing(&highscores);
+        assert!(listing.contains("Pro\n   1.    42  speed 120  2024-01-01  Q W E R\n"));
+        assert!(listing.starts_with("Normal\n  No runs yet\n"));
+        assert!(listing.contains(
+            "Time attack 30s\n   1.    25  speed  50  2024-01-01  Q W E R\nTime attack 60s\n  No runs yet\n"
+        ));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::spell_book::SpellBook;
+use crate::spells::SpellType;
+
+use std::path::Path;
+use std::time::Duration;
+use std::{fs, io::Read};
+
+use serde::{Deserialize, Serialize};
+
+const EMBEDDED_COMBOS: &str = include_str!("../resources/combos.toml");
+
+/// Spells of the combo drill that are cast one after another.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Combo {
+    pub name: String,
+    pub spells: Vec<SpellType>,
+}
+
+// A combo as written in combos.toml, spells can be given by id or name
+#[derive(Debug, Deserialize)]
+struct ComboEntry {
+    name: String,
+    spells: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComboFile {
+    combos: Vec<ComboEntry>,
+}
+
+/// Every combo the combo drill picks from.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ComboBook {
+    combos: Vec<Combo>,
+}
+
+impl Default for ComboBook {
+    fn default() -> Self {
+        Self::parse(EMBEDDED_COMBOS, &SpellBook::default()).expect("Embedded combos are valid")
+    }
+}
+
+impl ComboBook {
+    /// Reads combos.toml from `dir`, the directory of the settings file in use. Without one,
+    /// or if it can't be used, the combos that come with the game are used as far as
+    /// `spell_book` has their spells and what was wrong is returned.
+    pub fn load(dir: &Path, spell_book: &SpellBook) -> (Self, Vec<String>) {
+        Self::load_from(&dir.join("combos.toml"), spell_book)
+    }
+
+    fn load_from(path: &Path, spell_book: &SpellBook) -> (Self, Vec<String>) {
+        if !path.exists() {
+            return (Self::default().known_to(spell_book), Vec::new());
+        }
+
+        let mut contents = String::new();
+        let read = fs::File::open(path).and_then(|mut file| file.read_to_string(&mut contents));
+
+        let combo_book = read
+            .map_err(|e| e.into())
+            .and_then(|_| Self::parse(&contents, spell_book));
+        match combo_book {
+            Ok(combo_book) => (combo_book, Vec::new()),
+            Err(e) => (
+                Self::default().known_to(spell_book),
+                vec![format!(
+                    "Using the default combos, {} is invalid: {}",
+                    path.display(),
+                    e
+                )],
+            ),
+        }
+    }
+
+    /// Parses and validates a combo file: every combo has at least two spells
+    /// and all of them are in `spell_book`.
+    pub fn parse(
+        contents: &str,
+        spell_book: &SpellBook,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let file: ComboFile = toml::from_str(contents)?;
+        if file.combos.is_empty() {
+            return Err("There has to be at least one combo".into());
+        }
+
+        let mut combos = Vec::with_capacity(file.combos.len());
+        for entry in file.combos {
+            if entry.spells.len() < 2 {
+                return Err(format!("{} needs at least two spells", entry.name).into());
+            }
+
+            let mut spells = Vec::with_capacity(entry.spells.len());
+            for spell in &entry.spells {
+                let definition = spell_book.spells().iter().find(|definition| {
+                    definition.id == SpellType::new(spell)
+                        || definition.name.eq_ignore_ascii_case(spell)
+                });
+                match definition {
+                    Some(definition) => spells.push(definition.id.clone()),
+                    None => {
+                        return Err(format!("{} has an unknown spell {}", entry.name, spell).into())
+                    }
+                }
+            }
+
+            combos.push(Combo {
+                name: entry.name,
+                spells,
+            });
+        }
+
+        Ok(Self { combos })
+    }
+
+    pub fn combos(&self) -> &[Combo] {
+        &self.combos
+    }
+
+    // Custom spells may not have the ones a combo needs
+    fn known_to(mut self, spell_book: &SpellBook) -> Self {
+        self.combos.retain(|combo| {
+            combo
+                .spells
+                .iter()
+                .all(|spell| spell_book.spells().iter().any(|known| &known.id == spell))
+        });
+        self
+    }
+}
+
+/// A combo that was cast to the end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComboResult {
+    pub name: String,
+    // From its first spell showing up to its last one being cast
+    pub time: Duration,
+    pub key_presses: usize,
+    pub fewest_key_presses: usize,
+}
+
+/// One line for the game over screen, e.g. "3 combos, fastest Escape in 2.41s with 9 keys, fewest 9".
+pub fn summary(results: &[ComboResult]) -> String {
+    let fastest = match results.iter().min_by_key(|result| result.time) {
+        Some(fastest) => fastest,
+        None => return String::from("No combos completed"),
+    };
+
+    format!(
+        "{} combo{}, fastest {} in {:.2}s with {} keys, fewest {}",
+        results.len(),
+        if results.len() == 1 { "" } else { "s" },
+        fastest.name,
+        fastest.time.as_secs_f32(),
+        fastest.key_presses,
+        fastest.fewest_key_presses
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_combos() {
+        let combo_book = ComboBook::default();
+        let tornado = &combo_book.combos()[0];
+        assert_eq!(tornado.name, "Tornado combo");
+        assert_eq!(
+            tornado.spells,
+            ["Tornado", "Emp", "ChaosMeteor", "DeafeningBlast"].map(SpellType::new)
+        );
+    }
+
+    #[test]
+    fn test_custom_combos() {
+        let contents = r#"
+            [[combos]]
+            name = "Snap"
+            spells = ["Cold Snap", "ColdSnap", "sun strike"]
+        "#;
+
+        let combo_book = ComboBook::parse(contents, &SpellBook::default()).unwrap();
+        assert_eq!(
+            combo_book.combos()[0].spells,
+            ["ColdSnap", "ColdSnap", "SunStrike"].map(SpellType::new)
+        );
+    }
+
+    #[test]
+    fn test_invalid_combos() {
+        let spell_book = SpellBook::default();
+        let combo = |spells: &str| format!("[[combos]]\nname = \"Combo\"\nspells = [{}]\n", spells);
+
+        assert!(ComboBook::parse("combos = []", &spell_book).is_err());
+        assert!(ComboBook::parse(&combo("\"Tornado\""), &spell_book).is_err());
+        assert!(ComboBook::parse(&combo("\"Tornado\", \"Fireball\""), &spell_book).is_err());
+
+        // Without the spells they need, the built-in combos are left out
+        let spell_book = SpellBook::parse(
+            "[[spells]]\nname = \"Tornado\"\nrecipe = \"QWW\"\n\n[[spells]]\nname = \"Emp\"\nrecipe = \"WWW\"\n",
+        )
+        .unwrap();
+        assert!(ComboBook::default()
+            .known_to(&spell_book)
+            .combos()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_combo_file_is_reported() {
+        let path = std::env::temp_dir().join(format!("invoke-combos-{}.toml", std::process::id()));
+        fs::write(&path, "combos = \"none\"").unwrap();
+
+        let (combo_book, problems) = ComboBook::load_from(&path, &SpellBook::default());
+        assert_eq!(combo_book, ComboBook::default());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("Using the default combos"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_summary() {
+        let result = |name: &str, millis| ComboResult {
+            name: name.to_string(),
+            time: Duration::from_millis(millis),
+            key_presses: 12,
+            fewest_key_presses: 10,
+        };
+
+        assert_eq!(summary(&[]), "No combos completed");
+        assert_eq!(
+            summary(&[result("Escape", 3200), result("Tornado combo", 2410)]),
+            "2 combos, fastest Tornado combo in 2.41s with 12 keys, fewest 10"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::animations::RunAnimations;
+use crate::assets::{BACKGROUND_IMAGE, EXORT, INVOKE, QUAS, WEX};
+use crate::hud::Hud;
+use crate::layout::Layout;
+use crate::settings::Settings;
+use crate::simulation::Simulation;
+use crate::spell_book::SpellDefinition;
+use crate::spells::SpellType;
+
+use std::collections::HashMap;
+
+use ggez::{
+    glam::*,
+    graphics::{self, Canvas, Color, DrawParam, Rect},
+    Context, GameError, GameResult,
+};
+
+/// Draws a running `Simulation`: the background, the falling spells, the orb buffer and the HUD.
+pub struct GameRenderer {
+    background_image: graphics::Image,
+    background_draw_param: DrawParam,
+    spells: HashMap<SpellType, graphics::Image>,
+    orbs: HashMap<char, graphics::Image>,
+    layout: Layout,
+    hud: Hud,
+}
+
+impl GameRenderer {
+    pub fn new(ctx: &mut Context, settings: &Settings) -> GameResult<Self> {
+        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+
+        let spell_book = &settings.spell_book;
+        let mut spells = HashMap::with_capacity(spell_book.spells().len());
+        for spell in spell_book.spells() {
+            spells.insert(spell.id.clone(), Self::spell_icon(ctx, spell)?);
+        }
+
+        let mut orbs = HashMap::with_capacity(3);
+        orbs.insert('Q', graphics::Image::from_bytes(ctx, QUAS)?);
+        orbs.insert('W', graphics::Image::from_bytes(ctx, WEX)?);
+        orbs.insert('E', graphics::Image::from_bytes(ctx, EXORT)?);
+
+        Ok(Self {
+            background_image,
+            background_draw_param: settings.background_draw_param,
+            spells,
+            orbs,
+            layout: settings.layout(),
+            hud: Hud::new(settings),
+        })
+    }
+
+    // Images stay loaded, only their sizes and positions and the HUD change
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.background_draw_param = settings.background_draw_param;
+        self.layout = settings.layout();
+        self.hud = Hud::new(settings);
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, simulation: &Simulation, animations: &RunAnimations) {
+        canvas.draw(&self.background_image, self.background_draw_param);
+
+        let (shake_x, shake_y) = animations.shake();
+        let shake = Vec2::new(shake_x, shake_y) * self.layout.scale;
+
+        // Spells move in the pixels of the window the run started in,
+        // a window resized since then shows the same field stretched
+        let field = simulation.layout();
+        let field_scale = Vec2::new(
+            self.layout.width / field.width,
+            self.layout.height / field.height,
+        );
+        for spell in simulation.spells() {
+            let icon = &self.spells[&spell.spell_type];
+            // Custom icons can have any size, they take the space of a built-in one
+            let scale = self.layout.spell_size / icon.width() as f32;
+            let position = Vec2::new(spell.position.x, spell.position.y) * field_scale;
+            let draw_param = DrawParam::new()
+                .dest(position + shake)
+                .scale(Vec2::new(scale, scale));
+            canvas.draw(icon, draw_param);
+        }
+
+        // Cast spells grow around their center while they fade
+        for burst in animations.bursts() {
+            let icon = &self.spells[&burst.spell];
+            let fade = burst.fade();
+            let scale = self.layout.spell_size / icon.width() as f32 * fade.scale;
+            let grown = self.layout.spell_size * (fade.scale - 1.0) / 2.0;
+            let position = Vec2::new(burst.position.x, burst.position.y) * field_scale;
+            let draw_param = DrawParam::new()
+                .dest(position - Vec2::new(grown, grown) + shake)
+                .scale(Vec2::new(scale, scale))
+                .color(Color::new(1.0, 1.0, 1.0, fade.alpha));
+            canvas.draw(icon, draw_param);
+        }
+
+        let slot_distance = self.layout.orb_x[1] - self.layout.orb_x[0];
+        for (pos, key) in simulation.buffer().iter().enumerate() {
+            let orb_image = self.orbs.get(key).unwrap();
+
+            let scale = self.layout.orb_size / orb_image.width() as f32;
+            let x = self.layout.orb_x[pos] + animations.orb_offset(pos) * slot_distance;
+            let draw_param = DrawParam::new()
+                .dest(Vec2::new(x, self.layout.buffer_y) + shake)
+                .scale(Vec2::new(scale, scale));
+
+            canvas.draw(orb_image, draw_param);
+        }
+
+        self.hud
+            .draw(canvas, &self.layout, simulation, &self.spells);
+
+        let flash = animations.flash_alpha();
+        if flash > 0.0 {
+            let screen = Rect::new(0.0, 0.0, self.layout.width, self.layout.height);
+            canvas.draw(
+                &graphics::Quad,
+                DrawParam::new()
+                    .dest_rect(screen)
+                    .color(Color::new(0.8, 0.0, 0.0, flash)),
+            );
+        }
+    }
+
+    /// The icon from the spell file if it can be read, else the built-in one.
+    /// Spells without either are shown with the invoke icon.
+    pub fn spell_icon(ctx: &mut Context, spell: &SpellDefinition) -> GameResult<graphics::Image> {
+        if let Some(path) = &spell.icon {
+            let icon = std::fs::read(path)
+                .map_err(GameError::from)
+                .and_then(|bytes| graphics::Image::from_bytes(ctx, &bytes));
+            match icon {
+                Ok(icon) => return Ok(icon),
+                Err(e) => println!("Could not load icon {}: {}", path.display(), e),
+            }
+        }
+
+        let bytes = spell.id.embedded_image().unwrap_or(INVOKE);
+        graphics::Image::from_bytes(ctx, bytes)
+    }
+}
@@ -1,7 +1,9 @@
 use crate::assets::BACKGROUND_IMAGE;
 use crate::buttons::MenuButton;
+use crate::highscore_table::HighscoreTable;
+use crate::highscores::{GameMode, HighscoreEntry, Highscores};
 use crate::settings::Settings;
-use crate::state_machine::{GameState, Transition};
+use crate::state_machine::{GameState, Screen, Transition};
 
 use ggez::{
     glam::*,
@@ -12,20 +14,72 @@ use ggez::{
 
 pub struct GameOverProState {
     background_image: graphics::Image,
+    // One of the Pro modes
+    mode: GameMode,
+    score: usize,
+    personal_best: bool,
+    highscores: Highscores,
+    position: Option<usize>,
+    info: Option<String>,
     settings: Settings,
     buttons: Vec<MenuButton>,
+    highscore_table: HighscoreTable,
 }
 
 impl GameOverProState {
     pub fn new(
         ctx: &mut Context,
+        mode: GameMode,
         score: usize,
+        speed: f32,
         settings: &Settings,
         info: Option<String>,
     ) -> GameResult<Self> {
+        let entry = HighscoreEntry::new(score, speed, settings.keybinding_profile());
+        let (highscores, position, personal_best) = Highscores::record(mode, entry);
+
+        let (buttons, highscore_table) = Self::build(
+            ctx,
+            settings,
+            score,
+            personal_best,
+            highscores.entries(mode, None),
+            position,
+            info.as_deref(),
+        )?;
+        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+
+        Ok(Self {
+            background_image,
+            mode,
+            score,
+            personal_best,
+            highscores,
+            position,
+            info,
+            settings: settings.clone(),
+            buttons,
+            highscore_table,
+        })
+    }
+
+    // Everything that depends on the window size, the run is only recorded once in `new`
+    fn build(
+        ctx: &mut Context,
+        settings: &Settings,
+        score: usize,
+        personal_best: bool,
+        entries: &[HighscoreEntry],
+        position: Option<usize>,
+        info: Option<&str>,
+    ) -> GameResult<(Vec<MenuButton>, HighscoreTable)> {
         let mut buttons = Vec::new();
 
-        let game_over_text = format!("Score {}", score);
+        let game_over_text = if personal_best {
+            format!("Score {} - New personal best!", score)
+        } else {
+            format!("Score {}", score)
+        };
 
         let game_over_button_x = settings.window_width / 2.0;
         let game_over_button_y = settings.window_height / 3.0;
@@ -37,7 +91,7 @@ impl GameOverProState {
             settings,
             game_over_button_x,
             game_over_button_y,
-            None,
+            Some(1.5),
         )?;
 
         let go_to_menu_button_x = settings.window_width / 2.5;
@@ -71,7 +125,7 @@ impl GameOverProState {
             let info_button_y = settings.window_height / 2.0;
             let info_button = MenuButton::new(
                 ctx,
-                &info,
+                info,
                 "",
                 settings,
                 info_button_x,
@@ -84,17 +138,48 @@ impl GameOverProState {
         buttons.push(go_to_menu_button);
         buttons.push(try_again_button);
 
-        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+        let watch_replay_button_x = settings.window_width / 2.0;
+        let watch_replay_button_y = settings.window_height / 1.85;
+        let watch_replay_button = MenuButton::new(
+            ctx,
+            "Watch replay",
+            "V",
+            settings,
+            watch_replay_button_x,
+            watch_replay_button_y,
+            None,
+        )?;
+        buttons.push(watch_replay_button);
 
-        Ok(Self {
-            background_image,
-            settings: settings.clone(),
-            buttons,
-        })
+        let highscore_table = HighscoreTable::new(
+            ctx,
+            settings,
+            entries,
+            position,
+            settings.window_height * 0.6,
+        )?;
+
+        Ok((buttons, highscore_table))
     }
 }
 
 impl GameState for GameOverProState {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        let (buttons, highscore_table) = Self::build(
+            ctx,
+            settings,
+            self.score,
+            self.personal_best,
+            self.highscores.entries(self.mode, None),
+            self.position,
+            self.info.as_deref(),
+        )?;
+        self.buttons = buttons;
+        self.highscore_table = highscore_table;
+        self.settings = settings.clone();
+        Ok(())
+    }
+
     fn update(&mut self, _ctx: &mut Context) -> GameResult<Transition> {
         Ok(Transition::None)
     }
@@ -108,6 +193,7 @@ impl GameState for GameOverProState {
             canvas.draw(&button.text, button.text_draw_param);
             canvas.draw(&button.hint, button.hint_draw_param);
         }
+        self.highscore_table.draw(&mut canvas);
 
         canvas.finish(ctx)?;
         Ok(())
@@ -122,25 +208,39 @@ impl GameState for GameOverProState {
     ) -> GameResult<Transition> {
         if button == ggez::event::MouseButton::Left {
             let cursor_location = Vec2::new(x, y);
+            // The last three buttons, the Pro mode info button comes before them
+            let go_to_menu_button = &self.buttons[self.buttons.len() - 3];
             let go_to_menu_rect = Rect::new(
-                self.buttons[1].position.x,
-                self.buttons[1].position.y,
-                self.buttons[1].dimensions.w,
-                self.buttons[1].dimensions.h,
+                go_to_menu_button.position.x,
+                go_to_menu_button.position.y,
+                go_to_menu_button.dimensions.w,
+                go_to_menu_button.dimensions.h,
             );
 
+            let try_again_button = &self.buttons[self.buttons.len() - 2];
             let try_again_rect = Rect::new(
-                self.buttons[2].position.x,
-                self.buttons[2].position.y,
-                self.buttons[2].dimensions.w,
-                self.buttons[2].dimensions.h,
+                try_again_button.position.x,
+                try_again_button.position.y,
+                try_again_button.dimensions.w,
+                try_again_button.dimensions.h,
+            );
+
+            let watch_replay_button = &self.buttons[self.buttons.len() - 1];
+            let watch_replay_rect = Rect::new(
+                watch_replay_button.position.x,
+                watch_replay_button.position.y,
+                watch_replay_button.dimensions.w,
+                watch_replay_button.dimensions.h,
             );
 
             if go_to_menu_rect.contains(cursor_location) {
-                return Ok(Transition::Menu);
+                return Ok(Transition::Switch(Screen::Menu));
             }
             if try_again_rect.contains(cursor_location) {
-                return Ok(Transition::Game);
+                return Ok(Transition::run(self.mode));
+            }
+            if watch_replay_rect.contains(cursor_location) {
+                return Ok(Transition::WatchReplay { path: None });
             }
         }
 
@@ -153,9 +253,10 @@ impl GameState for GameOverProState {
         _repeat: bool,
     ) -> GameResult<Transition> {
         match keycode.keycode.unwrap() {
-            KeyCode::Return => return Ok(Transition::ProMode),
-            KeyCode::Escape => return Ok(Transition::Menu),
-            _ => return Ok(Transition::None),
+            KeyCode::Return => Ok(Transition::run(self.mode)),
+            KeyCode::Escape => Ok(Transition::Switch(Screen::Menu)),
+            KeyCode::V => Ok(Transition::WatchReplay { path: None }),
+            _ => Ok(Transition::None),
         }
     }
 }
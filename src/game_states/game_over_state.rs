@@ -1,7 +1,9 @@
 use crate::assets::BACKGROUND_IMAGE;
 use crate::buttons::MenuButton;
+use crate::highscore_table::HighscoreTable;
+use crate::highscores::{GameMode, HighscoreEntry, Highscores};
 use crate::settings::Settings;
-use crate::state_machine::{GameState, Transition};
+use crate::state_machine::{GameState, Screen, Transition};
 
 use ggez::{
     glam::*,
@@ -12,15 +14,68 @@ use ggez::{
 
 pub struct GameOverState {
     background_image: graphics::Image,
+    mode: GameMode,
+    score: usize,
+    personal_best: bool,
+    highscores: Highscores,
+    position: Option<usize>,
     settings: Settings,
     buttons: Vec<MenuButton>,
+    highscore_table: HighscoreTable,
 }
 
 impl GameOverState {
-    pub fn new(ctx: &mut Context, score: usize, settings: &Settings) -> GameResult<Self> {
+    pub fn new(
+        ctx: &mut Context,
+        mode: GameMode,
+        score: usize,
+        speed: f32,
+        settings: &Settings,
+    ) -> GameResult<Self> {
+        let entry = HighscoreEntry::new(score, speed, settings.keybinding_profile());
+        let (highscores, position, personal_best) = Highscores::record(mode, entry);
+
+        let (buttons, highscore_table) = Self::build(
+            ctx,
+            settings,
+            mode,
+            score,
+            personal_best,
+            &highscores,
+            position,
+        )?;
+        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+
+        Ok(Self {
+            background_image,
+            mode,
+            score,
+            personal_best,
+            highscores,
+            position,
+            settings: settings.clone(),
+            buttons,
+            highscore_table,
+        })
+    }
+
+    // Everything that depends on the window size, the run is only recorded once in `new`
+    fn build(
+        ctx: &mut Context,
+        settings: &Settings,
+        mode: GameMode,
+        score: usize,
+        personal_best: bool,
+        highscores: &Highscores,
+        position: Option<usize>,
+    ) -> GameResult<(Vec<MenuButton>, HighscoreTable)> {
         let mut buttons = Vec::new();
 
-        let game_over_text = format!("Score {}", score);
+        let game_over_text = if personal_best {
+            format!("Score {} - New personal best!", score)
+        } else {
+            format!("Score {}", score)
+        };
 
         let game_over_button_x = settings.window_width / 2.0;
         let game_over_button_y = settings.window_height / 3.0;
@@ -32,7 +87,7 @@ impl GameOverState {
             settings,
             game_over_button_x,
             game_over_button_y,
-            None,
+            Some(1.5),
         )?;
 
         let go_to_menu_button_x = settings.window_width / 2.5;
@@ -49,9 +104,14 @@ impl GameOverState {
 
         let try_again_button_x = settings.window_width / 1.5;
         let try_again_button_y = settings.window_height / 2.5;
+        let try_again_text = if mode == GameMode::Daily {
+            "Play again"
+        } else {
+            "Try again"
+        };
         let try_again_button = MenuButton::new(
             ctx,
-            "Try again",
+            try_again_text,
             "RET",
             settings,
             try_again_button_x,
@@ -63,17 +123,48 @@ impl GameOverState {
         buttons.push(go_to_menu_button);
         buttons.push(try_again_button);
 
-        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+        let watch_replay_button_x = settings.window_width / 2.0;
+        let watch_replay_button_y = settings.window_height / 2.0;
+        let watch_replay_button = MenuButton::new(
+            ctx,
+            "Watch replay",
+            "V",
+            settings,
+            watch_replay_button_x,
+            watch_replay_button_y,
+            None,
+        )?;
+        buttons.push(watch_replay_button);
 
-        Ok(Self {
-            background_image,
-            settings: settings.clone(),
-            buttons,
-        })
+        let highscore_table = HighscoreTable::new(
+            ctx,
+            settings,
+            highscores.entries(mode, None),
+            position,
+            settings.window_height * 0.6,
+        )?;
+
+        Ok((buttons, highscore_table))
     }
 }
 
 impl GameState for GameOverState {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        let (buttons, highscore_table) = Self::build(
+            ctx,
+            settings,
+            self.mode,
+            self.score,
+            self.personal_best,
+            &self.highscores,
+            self.position,
+        )?;
+        self.buttons = buttons;
+        self.highscore_table = highscore_table;
+        self.settings = settings.clone();
+        Ok(())
+    }
+
     fn update(&mut self, _ctx: &mut Context) -> GameResult<Transition> {
         Ok(Transition::None)
     }
@@ -87,6 +178,7 @@ impl GameState for GameOverState {
             canvas.draw(&button.text, button.text_draw_param);
             canvas.draw(&button.hint, button.hint_draw_param);
         }
+        self.highscore_table.draw(&mut canvas);
 
         canvas.finish(ctx)?;
         Ok(())
@@ -115,11 +207,22 @@ impl GameState for GameOverState {
                 self.buttons[2].dimensions.h,
             );
 
+            let watch_replay_button = &self.buttons[3];
+            let watch_replay_rect = Rect::new(
+                watch_replay_button.position.x,
+                watch_replay_button.position.y,
+                watch_replay_button.dimensions.w,
+                watch_replay_button.dimensions.h,
+            );
+
             if go_to_menu_rect.contains(cursor_location) {
-                return Ok(Transition::Menu);
+                return Ok(Transition::Switch(Screen::Menu));
             }
             if try_again_rect.contains(cursor_location) {
-                return Ok(Transition::Game);
+                return Ok(Transition::run(self.mode));
+            }
+            if watch_replay_rect.contains(cursor_location) {
+                return Ok(Transition::WatchReplay { path: None });
             }
         }
 
@@ -132,9 +235,10 @@ impl GameState for GameOverState {
         _repeat: bool,
     ) -> GameResult<Transition> {
         match keycode.keycode.unwrap() {
-            KeyCode::Return => return Ok(Transition::Game),
-            KeyCode::Escape => return Ok(Transition::Menu),
-            _ => return Ok(Transition::None),
+            KeyCode::Return => Ok(Transition::run(self.mode)),
+            KeyCode::Escape => Ok(Transition::Switch(Screen::Menu)),
+            KeyCode::V => Ok(Transition::WatchReplay { path: None }),
+            _ => Ok(Transition::None),
         }
     }
 }
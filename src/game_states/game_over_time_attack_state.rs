@@ -0,0 +1,254 @@
+use crate::assets::BACKGROUND_IMAGE;
+use crate::buttons::MenuButton;
+use crate::highscore_table::HighscoreTable;
+use crate::highscores::{GameMode, HighscoreEntry, Highscores};
+use crate::settings::Settings;
+use crate::simulation::TimeAttackResult;
+use crate::state_machine::{GameState, Screen, Transition};
+
+use ggez::{
+    glam::*,
+    graphics::{self, Color, Rect},
+    input::keyboard::{KeyCode, KeyInput},
+    Context, GameResult,
+};
+
+pub struct GameOverTimeAttackState {
+    background_image: graphics::Image,
+    result: TimeAttackResult,
+    personal_best: bool,
+    highscores: Highscores,
+    position: Option<usize>,
+    settings: Settings,
+    buttons: Vec<MenuButton>,
+    highscore_table: HighscoreTable,
+}
+
+impl GameOverTimeAttackState {
+    pub fn new(
+        ctx: &mut Context,
+        result: TimeAttackResult,
+        settings: &Settings,
+    ) -> GameResult<Self> {
+        let time_limit = Some(result.time_limit.as_secs());
+        let entry = HighscoreEntry::timed(
+            result.spells,
+            result.time_limit.as_secs(),
+            settings.keybinding_profile(),
+        );
+        let (highscores, position, personal_best) = Highscores::record(GameMode::TimeAttack, entry);
+
+        let (buttons, highscore_table) = Self::build(
+            ctx,
+            settings,
+            &result,
+            personal_best,
+            highscores.entries(GameMode::TimeAttack, time_limit),
+            position,
+        )?;
+        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+
+        Ok(Self {
+            background_image,
+            result,
+            personal_best,
+            highscores,
+            position,
+            settings: settings.clone(),
+            buttons,
+            highscore_table,
+        })
+    }
+
+    // Everything that depends on the window size, the run is only recorded once in `new`
+    fn build(
+        ctx: &mut Context,
+        settings: &Settings,
+        result: &TimeAttackResult,
+        personal_best: bool,
+        entries: &[HighscoreEntry],
+        position: Option<usize>,
+    ) -> GameResult<(Vec<MenuButton>, HighscoreTable)> {
+        let mut buttons = Vec::new();
+
+        let game_over_text = if personal_best {
+            format!(
+                "{:.0} spells per minute - New personal best!",
+                result.spells_per_minute()
+            )
+        } else {
+            format!("{:.0} spells per minute", result.spells_per_minute())
+        };
+
+        let game_over_button_x = settings.window_width / 2.0;
+        let game_over_button_y = settings.window_height / 3.0;
+        let game_over_button = MenuButton::new(
+            ctx,
+            &game_over_text,
+            "",
+            settings,
+            game_over_button_x,
+            game_over_button_y,
+            Some(1.5),
+        )?;
+
+        let go_to_menu_button_x = settings.window_width / 2.5;
+        let go_to_menu_button_y = settings.window_height / 2.5;
+        let go_to_menu_button = MenuButton::new(
+            ctx,
+            "Menu",
+            "ESC",
+            settings,
+            go_to_menu_button_x,
+            go_to_menu_button_y,
+            None,
+        )?;
+
+        let try_again_button_x = settings.window_width / 1.5;
+        let try_again_button_y = settings.window_height / 2.5;
+        let try_again_button = MenuButton::new(
+            ctx,
+            "Try again",
+            "RET",
+            settings,
+            try_again_button_x,
+            try_again_button_y,
+            None,
+        )?;
+
+        let info_button_x = settings.window_width / 2.0;
+        let info_button_y = settings.window_height / 2.0;
+        let info_button = MenuButton::new(
+            ctx,
+            &result.summary(),
+            "",
+            settings,
+            info_button_x,
+            info_button_y,
+            Some(1.5),
+        )?;
+
+        let watch_replay_button_x = settings.window_width / 2.0;
+        let watch_replay_button_y = settings.window_height / 1.85;
+        let watch_replay_button = MenuButton::new(
+            ctx,
+            "Watch replay",
+            "V",
+            settings,
+            watch_replay_button_x,
+            watch_replay_button_y,
+            None,
+        )?;
+
+        buttons.push(game_over_button);
+        buttons.push(info_button);
+        buttons.push(go_to_menu_button);
+        buttons.push(try_again_button);
+        buttons.push(watch_replay_button);
+
+        let highscore_table = HighscoreTable::new(
+            ctx,
+            settings,
+            entries,
+            position,
+            settings.window_height * 0.6,
+        )?;
+
+        Ok((buttons, highscore_table))
+    }
+}
+
+impl GameState for GameOverTimeAttackState {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        let (buttons, highscore_table) = Self::build(
+            ctx,
+            settings,
+            &self.result,
+            self.personal_best,
+            self.highscores
+                .entries(GameMode::TimeAttack, Some(self.result.time_limit.as_secs())),
+            self.position,
+        )?;
+        self.buttons = buttons;
+        self.highscore_table = highscore_table;
+        self.settings = settings.clone();
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+        canvas.draw(&self.background_image, self.settings.background_draw_param);
+
+        for button in &self.buttons {
+            canvas.draw(&button.background, button.draw_param);
+            canvas.draw(&button.text, button.text_draw_param);
+            canvas.draw(&button.hint, button.hint_draw_param);
+        }
+        self.highscore_table.draw(&mut canvas);
+
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: ggez::event::MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult<Transition> {
+        if button == ggez::event::MouseButton::Left {
+            let cursor_location = Vec2::new(x, y);
+            let go_to_menu_rect = Rect::new(
+                self.buttons[2].position.x,
+                self.buttons[2].position.y,
+                self.buttons[2].dimensions.w,
+                self.buttons[2].dimensions.h,
+            );
+
+            let try_again_rect = Rect::new(
+                self.buttons[3].position.x,
+                self.buttons[3].position.y,
+                self.buttons[3].dimensions.w,
+                self.buttons[3].dimensions.h,
+            );
+
+            let watch_replay_rect = Rect::new(
+                self.buttons[4].position.x,
+                self.buttons[4].position.y,
+                self.buttons[4].dimensions.w,
+                self.buttons[4].dimensions.h,
+            );
+
+            if go_to_menu_rect.contains(cursor_location) {
+                return Ok(Transition::Switch(Screen::Menu));
+            }
+            if try_again_rect.contains(cursor_location) {
+                return Ok(Transition::run(GameMode::TimeAttack));
+            }
+            if watch_replay_rect.contains(cursor_location) {
+                return Ok(Transition::WatchReplay { path: None });
+            }
+        }
+
+        Ok(Transition::None)
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyInput,
+        _repeat: bool,
+    ) -> GameResult<Transition> {
+        match keycode.keycode {
+            Some(KeyCode::Return) => Ok(Transition::run(GameMode::TimeAttack)),
+            Some(KeyCode::Escape) => Ok(Transition::Switch(Screen::Menu)),
+            Some(KeyCode::V) => Ok(Transition::WatchReplay { path: None }),
+            _ => Ok(Transition::None),
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use crate::assets::BACKGROUND_IMAGE;
 use crate::buttons::MenuButton;
+use crate::highscores::GameMode;
 use crate::settings::Settings;
-use crate::state_machine::{GameState, Transition};
+use crate::state_machine::{GameState, Screen, Transition};
 
 use ggez::{
     glam::*,
@@ -9,19 +10,26 @@ use ggez::{
     input::keyboard::{KeyCode, KeyInput},
     Context, GameResult,
 };
+use keyframe::{ease, functions::EaseOut};
 
 pub struct MenuState {
     background_image: graphics::Image,
     settings: Settings,
     buttons: Vec<MenuButton>,
+    // Seconds since the menu was opened, the buttons ease in one after another
+    shown_for: f32,
 }
 
+// Seconds one button takes to ease in and between two buttons starting
+const EASE_IN_DURATION: f32 = 0.3;
+const EASE_IN_DELAY: f32 = 0.05;
+
 impl MenuState {
     pub fn new(ctx: &mut Context, settings: &Settings) -> GameResult<Self> {
         let mut buttons = Vec::new();
 
         let button_x = settings.window_width / 2.0;
-        let play_button_y = settings.window_height / 3.0;
+        let play_button_y = settings.window_height / 3.333;
         let play_button = MenuButton::new(
             ctx,
             "Start Game",
@@ -32,7 +40,7 @@ impl MenuState {
             None,
         )?;
 
-        let pro_mode_button_y = settings.window_height / 2.5;
+        let pro_mode_button_y = settings.window_height / 2.727;
         let pro_mode_button = MenuButton::new(
             ctx,
             "ProMode",
@@ -43,12 +51,96 @@ impl MenuState {
             None,
         )?;
 
-        let quit_button_y = settings.window_height / 2.14;
+        let daily_challenge_button_y = settings.window_height / 2.308;
+        let daily_challenge_button = MenuButton::new(
+            ctx,
+            "Daily Challenge",
+            "D",
+            settings,
+            button_x,
+            daily_challenge_button_y,
+            None,
+        )?;
+
+        let slot_mode_button_y = settings.window_height / 2.0;
+        let slot_mode_button = MenuButton::new(
+            ctx,
+            "Slot Mode",
+            "L",
+            settings,
+            button_x,
+            slot_mode_button_y,
+            None,
+        )?;
+
+        let graded_pro_button_y = settings.window_height / 1.765;
+        let graded_pro_button = MenuButton::new(
+            ctx,
+            "Graded Pro",
+            "G",
+            settings,
+            button_x,
+            graded_pro_button_y,
+            None,
+        )?;
+
+        let combo_drill_button_y = settings.window_height / 1.579;
+        let combo_drill_button = MenuButton::new(
+            ctx,
+            "Combo Drill",
+            "C",
+            settings,
+            button_x,
+            combo_drill_button_y,
+            None,
+        )?;
+
+        let time_attack_button_y = settings.window_height / 1.429;
+        let time_attack_button = MenuButton::new(
+            ctx,
+            "Time Attack",
+            "T",
+            settings,
+            button_x,
+            time_attack_button_y,
+            None,
+        )?;
+
+        let statistics_button_y = settings.window_height / 1.304;
+        let statistics_button = MenuButton::new(
+            ctx,
+            "Statistics",
+            "S",
+            settings,
+            button_x,
+            statistics_button_y,
+            None,
+        )?;
+
+        let settings_button_y = settings.window_height / 1.2;
+        let settings_button = MenuButton::new(
+            ctx,
+            "Settings",
+            "O",
+            settings,
+            button_x,
+            settings_button_y,
+            None,
+        )?;
+
+        let quit_button_y = settings.window_height / 1.111;
         let quit_button =
             MenuButton::new(ctx, "Quit", "ESC", settings, button_x, quit_button_y, None)?;
 
         buttons.push(play_button);
         buttons.push(pro_mode_button);
+        buttons.push(daily_challenge_button);
+        buttons.push(slot_mode_button);
+        buttons.push(graded_pro_button);
+        buttons.push(combo_drill_button);
+        buttons.push(time_attack_button);
+        buttons.push(statistics_button);
+        buttons.push(settings_button);
         buttons.push(quit_button);
 
         let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
@@ -57,12 +149,21 @@ impl MenuState {
             background_image,
             settings: settings.clone(),
             buttons,
+            shown_for: 0.0,
         })
     }
 }
 
 impl GameState for MenuState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult<Transition> {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        let shown_for = self.shown_for;
+        *self = Self::new(ctx, settings)?;
+        self.shown_for = shown_for;
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<Transition> {
+        self.shown_for += ctx.time.delta().as_secs_f32();
         Ok(Transition::None)
     }
 
@@ -72,10 +173,11 @@ impl GameState for MenuState {
 
         // That drove me mad untill I found this:
         // https://github.com/ggez/ggez/issues/659
-        for button in &self.buttons {
-            canvas.draw(&button.background, button.draw_param);
-            canvas.draw(&button.text, button.text_draw_param);
-            canvas.draw(&button.hint, button.hint_draw_param);
+        for (index, button) in self.buttons.iter().enumerate() {
+            let time = (self.shown_for - index as f32 * EASE_IN_DELAY) / EASE_IN_DURATION;
+            let eased = ease(EaseOut, 0.0, 1.0, time.clamp(0.0, 1.0));
+            let offset = Vec2::new(0.0, (1.0 - eased) * self.settings.window_height * 0.05);
+            button.draw_moved(&mut canvas, offset, eased);
         }
         canvas.finish(ctx)?;
         Ok(())
@@ -104,19 +206,96 @@ impl GameState for MenuState {
                 self.buttons[1].dimensions.h,
             );
 
-            let quit_game_rect = Rect::new(
+            let daily_challenge_rect = Rect::new(
                 self.buttons[2].position.x,
                 self.buttons[2].position.y,
                 self.buttons[2].dimensions.w,
                 self.buttons[2].dimensions.h,
             );
 
+            let slot_mode_rect = Rect::new(
+                self.buttons[3].position.x,
+                self.buttons[3].position.y,
+                self.buttons[3].dimensions.w,
+                self.buttons[3].dimensions.h,
+            );
+
+            let graded_pro_rect = Rect::new(
+                self.buttons[4].position.x,
+                self.buttons[4].position.y,
+                self.buttons[4].dimensions.w,
+                self.buttons[4].dimensions.h,
+            );
+
+            let combo_drill_rect = Rect::new(
+                self.buttons[5].position.x,
+                self.buttons[5].position.y,
+                self.buttons[5].dimensions.w,
+                self.buttons[5].dimensions.h,
+            );
+
+            let time_attack_rect = Rect::new(
+                self.buttons[6].position.x,
+                self.buttons[6].position.y,
+                self.buttons[6].dimensions.w,
+                self.buttons[6].dimensions.h,
+            );
+
+            let statistics_rect = Rect::new(
+                self.buttons[7].position.x,
+                self.buttons[7].position.y,
+                self.buttons[7].dimensions.w,
+                self.buttons[7].dimensions.h,
+            );
+
+            let settings_rect = Rect::new(
+                self.buttons[8].position.x,
+                self.buttons[8].position.y,
+                self.buttons[8].dimensions.w,
+                self.buttons[8].dimensions.h,
+            );
+
+            let quit_game_rect = Rect::new(
+                self.buttons[9].position.x,
+                self.buttons[9].position.y,
+                self.buttons[9].dimensions.w,
+                self.buttons[9].dimensions.h,
+            );
+
             if start_game_rect.contains(cursor_location) {
-                return Ok(Transition::Game);
+                return Ok(Transition::run(GameMode::Normal));
             }
 
             if pro_mode_rect.contains(cursor_location) {
-                return Ok(Transition::ProMode);
+                return Ok(Transition::run(GameMode::Pro));
+            }
+
+            if daily_challenge_rect.contains(cursor_location) {
+                return Ok(Transition::run(GameMode::Daily));
+            }
+
+            if slot_mode_rect.contains(cursor_location) {
+                return Ok(Transition::run(GameMode::Slots));
+            }
+
+            if graded_pro_rect.contains(cursor_location) {
+                return Ok(Transition::run(GameMode::GradedPro));
+            }
+
+            if combo_drill_rect.contains(cursor_location) {
+                return Ok(Transition::run(GameMode::Combos));
+            }
+
+            if time_attack_rect.contains(cursor_location) {
+                return Ok(Transition::run(GameMode::TimeAttack));
+            }
+
+            if statistics_rect.contains(cursor_location) {
+                return Ok(Transition::Switch(Screen::Statistics));
+            }
+
+            if settings_rect.contains(cursor_location) {
+                return Ok(Transition::Switch(Screen::Settings));
             }
 
             if quit_game_rect.contains(cursor_location) {
@@ -132,10 +311,17 @@ impl GameState for MenuState {
         _repeat: bool,
     ) -> GameResult<Transition> {
         match keycode.keycode.unwrap() {
-            KeyCode::Return => return Ok(Transition::Game),
-            KeyCode::Escape => return Ok(Transition::Quit),
-            KeyCode::P => return Ok(Transition::ProMode),
-            _ => return Ok(Transition::None),
+            KeyCode::Return => Ok(Transition::run(GameMode::Normal)),
+            KeyCode::Escape => Ok(Transition::Quit),
+            KeyCode::P => Ok(Transition::run(GameMode::Pro)),
+            KeyCode::D => Ok(Transition::run(GameMode::Daily)),
+            KeyCode::L => Ok(Transition::run(GameMode::Slots)),
+            KeyCode::G => Ok(Transition::run(GameMode::GradedPro)),
+            KeyCode::C => Ok(Transition::run(GameMode::Combos)),
+            KeyCode::T => Ok(Transition::run(GameMode::TimeAttack)),
+            KeyCode::S => Ok(Transition::Switch(Screen::Statistics)),
+            KeyCode::O => Ok(Transition::Switch(Screen::Settings)),
+            _ => Ok(Transition::None),
         }
     }
 }
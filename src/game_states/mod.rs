@@ -1,6 +1,11 @@
 pub mod game_over_pro_mode_state;
 pub mod game_over_state;
+pub mod game_over_time_attack_state;
 pub mod menu_state;
+pub mod notice_state;
+pub mod pause_state;
 pub mod play_state;
-pub mod pro_mode_state;
+pub mod replay_state;
 pub mod settings_state;
+pub mod statistics_state;
+pub mod time_attack_state;
@@ -0,0 +1,147 @@
+use crate::assets::BACKGROUND_IMAGE;
+use crate::buttons::MenuButton;
+use crate::settings::Settings;
+use crate::state_machine::{GameState, Screen, Transition};
+
+use ggez::{
+    glam::*,
+    graphics::{self, Color, DrawParam, Drawable, Rect},
+    input::keyboard::{KeyCode, KeyInput},
+    Context, GameResult,
+};
+
+/// Tells the player about problems found while starting the game, e.g. settings
+/// that had to be replaced by their defaults.
+pub struct NoticeState {
+    background_image: graphics::Image,
+    settings: Settings,
+    buttons: Vec<MenuButton>,
+    lines: Vec<(graphics::Text, DrawParam)>,
+    title: String,
+    notices: Vec<String>,
+}
+
+impl NoticeState {
+    pub fn new(
+        ctx: &mut Context,
+        settings: &Settings,
+        title: &str,
+        notices: &[String],
+    ) -> GameResult<Self> {
+        let title_button = MenuButton::new(
+            ctx,
+            title,
+            "",
+            settings,
+            settings.window_width / 2.0,
+            settings.window_height / 12.0,
+            Some(2.0),
+        )?;
+
+        let continue_button = MenuButton::new(
+            ctx,
+            "Continue",
+            "RET",
+            settings,
+            settings.window_width / 2.0,
+            settings.window_height * 0.85,
+            None,
+        )?;
+
+        let font_size = settings.font_size * 0.8;
+        let mut lines = Vec::with_capacity(notices.len());
+        let mut y = settings.window_height * 0.2;
+        for notice in notices {
+            // Long notices wrap, so every line starts below the previous one
+            let text = graphics::Text::new(notice.as_str())
+                .set_scale(font_size)
+                .set_bounds(Vec2::new(settings.window_width * 0.8, f32::INFINITY))
+                .clone();
+            let height = text
+                .dimensions(ctx)
+                .expect("Text Drawable always has dimensions")
+                .h;
+            let dest = Vec2::new(settings.window_width * 0.1, y);
+            lines.push((text, DrawParam::new().dest(dest)));
+            y += height + font_size * 0.5;
+        }
+
+        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+
+        Ok(Self {
+            background_image,
+            settings: settings.clone(),
+            buttons: vec![title_button, continue_button],
+            lines,
+            title: title.to_string(),
+            notices: notices.to_vec(),
+        })
+    }
+}
+
+impl GameState for NoticeState {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        let title = std::mem::take(&mut self.title);
+        let notices = std::mem::take(&mut self.notices);
+        *self = Self::new(ctx, settings, &title, &notices)?;
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+        canvas.draw(&self.background_image, self.settings.background_draw_param);
+
+        for button in &self.buttons {
+            canvas.draw(&button.background, button.draw_param);
+            canvas.draw(&button.text, button.text_draw_param);
+            canvas.draw(&button.hint, button.hint_draw_param);
+        }
+
+        for (text, draw_param) in &self.lines {
+            canvas.draw(text, *draw_param);
+        }
+
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: ggez::event::MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult<Transition> {
+        if button == ggez::event::MouseButton::Left {
+            let cursor_location = Vec2::new(x, y);
+            let continue_rect = Rect::new(
+                self.buttons[1].position.x,
+                self.buttons[1].position.y,
+                self.buttons[1].dimensions.w,
+                self.buttons[1].dimensions.h,
+            );
+
+            if continue_rect.contains(cursor_location) {
+                return Ok(Transition::Switch(Screen::Menu));
+            }
+        }
+
+        Ok(Transition::None)
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyInput,
+        _repeat: bool,
+    ) -> GameResult<Transition> {
+        match keycode.keycode {
+            Some(KeyCode::Return) | Some(KeyCode::Escape) => Ok(Transition::Switch(Screen::Menu)),
+            _ => Ok(Transition::None),
+        }
+    }
+}
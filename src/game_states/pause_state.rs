@@ -0,0 +1,183 @@
+use crate::buttons::MenuButton;
+use crate::highscores::GameMode;
+use crate::settings::Settings;
+use crate::state_machine::{GameState, Overlay, Screen, Transition};
+
+use ggez::{
+    glam::*,
+    graphics::{self, Color, DrawParam, Rect},
+    input::keyboard::{KeyCode, KeyInput},
+    Context, GameResult,
+};
+
+/// Sits on top of a run while it is paused, the run stays visible but frozen underneath.
+pub struct PauseState {
+    mode: GameMode,
+    settings: Settings,
+    title: MenuButton,
+    buttons: Vec<MenuButton>,
+    // Letters could be bound to orbs, so buttons are picked with the arrow keys
+    selected: usize,
+}
+
+impl PauseState {
+    pub fn new(ctx: &mut Context, settings: &Settings, mode: GameMode) -> GameResult<Self> {
+        let title = MenuButton::new(
+            ctx,
+            "Paused",
+            "",
+            settings,
+            settings.window_width / 2.0,
+            settings.window_height / 4.0,
+            Some(2.0),
+        )?;
+
+        let button_x = settings.window_width / 2.0;
+        let mut buttons = Vec::new();
+        for (index, (text, hint)) in [
+            ("Resume", "ESC"),
+            ("Restart", ""),
+            ("Settings", ""),
+            ("Quit to menu", ""),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let button_y = settings.window_height * (0.4 + 0.07 * index as f32);
+            buttons.push(MenuButton::new(
+                ctx, text, hint, settings, button_x, button_y, None,
+            )?);
+        }
+
+        Ok(Self {
+            mode,
+            settings: settings.clone(),
+            title,
+            buttons,
+            selected: 0,
+        })
+    }
+
+    fn button_at(&self, x: f32, y: f32) -> Option<usize> {
+        let cursor_location = Vec2::new(x, y);
+        self.buttons.iter().position(|button| {
+            Rect::new(
+                button.position.x,
+                button.position.y,
+                button.dimensions.w,
+                button.dimensions.h,
+            )
+            .contains(cursor_location)
+        })
+    }
+
+    // What the button at `index` does, in the order they are drawn
+    fn action(&self, index: usize) -> Transition {
+        match index {
+            0 => Transition::Pop,
+            // A fresh run of the same mode, the paused one is recorded as quit
+            1 => Transition::run(self.mode),
+            2 => Transition::Push(Overlay::Settings),
+            _ => Transition::Switch(Screen::Menu),
+        }
+    }
+}
+
+impl GameState for PauseState {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        let selected = self.selected;
+        *self = Self::new(ctx, settings, self.mode)?;
+        self.selected = selected;
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        // No clear color, the frozen run was drawn into this frame already
+        let mut canvas = graphics::Canvas::from_frame(ctx, None);
+
+        let screen = Rect::new(
+            0.0,
+            0.0,
+            self.settings.window_width,
+            self.settings.window_height,
+        );
+        canvas.draw(
+            &graphics::Quad,
+            DrawParam::new()
+                .dest_rect(screen)
+                .color(Color::new(0.0, 0.0, 0.0, 0.6)),
+        );
+
+        canvas.draw(&self.title.background, self.title.draw_param);
+        canvas.draw(&self.title.text, self.title.text_draw_param);
+
+        for (index, button) in self.buttons.iter().enumerate() {
+            let background_color = if index == self.selected {
+                Color::from_rgb(255, 215, 120)
+            } else {
+                Color::WHITE
+            };
+            canvas.draw(
+                &button.background,
+                button.draw_param.color(background_color),
+            );
+            canvas.draw(&button.text, button.text_draw_param);
+            canvas.draw(&button.hint, button.hint_draw_param);
+        }
+
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: ggez::event::MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult<Transition> {
+        if button != ggez::event::MouseButton::Left {
+            return Ok(Transition::None);
+        }
+
+        match self.button_at(x, y) {
+            Some(index) => Ok(self.action(index)),
+            None => Ok(Transition::None),
+        }
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult<Transition> {
+        if let Some(index) = self.button_at(x, y) {
+            self.selected = index;
+        }
+        Ok(Transition::None)
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyInput,
+        _repeat: bool,
+    ) -> GameResult<Transition> {
+        let count = self.buttons.len();
+        match keycode.keycode {
+            Some(KeyCode::Up) => self.selected = (self.selected + count - 1) % count,
+            Some(KeyCode::Down) => self.selected = (self.selected + 1) % count,
+            Some(KeyCode::Return) => return Ok(self.action(self.selected)),
+            Some(KeyCode::Escape) => return Ok(Transition::Pop),
+            _ => {}
+        }
+        Ok(Transition::None)
+    }
+}
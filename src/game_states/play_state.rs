@@ -1,103 +1,234 @@
-use crate::assets::BACKGROUND_IMAGE;
-use crate::input_buffer::InputBuffer;
-use crate::orbs::{Orb, OrbType};
+use crate::animations::RunAnimations;
+use crate::audio::Audio;
+use crate::bindings::Binding;
+use crate::combo_book::{self, ComboBook};
+use crate::game_renderer::GameRenderer;
+use crate::highscores::GameMode;
+use crate::orbs::OrbType;
+use crate::replay::Replay;
+use crate::run_history::{RunEnd, RunRecorder};
 use crate::settings::Settings;
-use crate::spells::Spell;
-use crate::state_machine::{GameState, Transition};
+use crate::simulation::{Event, Simulation};
+use crate::spawner::Spawner;
+use crate::spell_book::SpellBook;
+use crate::state_machine::{GameState, Overlay, Screen, Transition, TICKS_PER_SECOND};
 
 use std::collections::HashMap;
 
 use ggez::{
-    glam::*,
     graphics::{self},
     input::keyboard::{KeyCode, KeyInput},
     Context, GameResult,
 };
 
+/// A run of any mode, the modes only differ in their simulation and game over screen.
 pub struct MainState {
-    game_over: bool,
-    objects: Vec<Spell>,
-    input_buffer: InputBuffer,
-    score: usize,
-    speed: f32,
-    last_spell_time: std::time::Duration,
+    ticks: u64,
+    simulation: Simulation,
+    run: RunRecorder,
+    replay: Replay,
+    renderer: GameRenderer,
+    audio: Audio,
+    animations: RunAnimations,
+    keybindings: HashMap<Binding, OrbType>,
+    // The hint on the Pro game over screen names the keys that are bound
     settings: Settings,
-    background_image: graphics::Image,
-    keybindings: HashMap<KeyCode, Orb>,
-    orbs: HashMap<char, graphics::Image>,
 }
 
 impl MainState {
-    pub fn new(settings: Settings, ctx: &mut Context) -> GameResult<Self> {
-        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+    pub fn new(
+        settings: Settings,
+        ctx: &mut Context,
+        mode: GameMode,
+        seed: u64,
+    ) -> GameResult<Self> {
+        // The daily challenge has to be the same for everyone, custom spells included
+        let mut settings = settings;
+        let spawner = if mode == GameMode::Daily {
+            settings.spell_book = SpellBook::default();
+            settings.combo_book = ComboBook::default();
+            Spawner::uniform()
+        } else {
+            Spawner::new(settings.adaptive_spawning, &settings.spell_book)
+        };
 
-        let quas = Orb::new(ctx, OrbType::Quas)?;
-        let wex = Orb::new(ctx, OrbType::Wex)?;
-        let exort = Orb::new(ctx, OrbType::Exort)?;
-        let invoke = Orb::new(ctx, OrbType::Invoke)?;
+        let simulation = Simulation::new(
+            mode,
+            seed,
+            spawner.clone(),
+            settings.spell_book.clone(),
+            settings.combo_book.clone(),
+            settings.window_width,
+            settings.window_height,
+        );
+        let replay = Replay::new(mode, seed, &settings, spawner);
 
-        let quas_image = quas.orb_image.clone();
-        let wex_image = wex.orb_image.clone();
-        let exort_image = exort.orb_image.clone();
+        Ok(Self {
+            ticks: 0,
+            simulation,
+            run: RunRecorder::new(mode, seed),
+            replay,
+            renderer: GameRenderer::new(ctx, &settings)?,
+            audio: Audio::new(ctx, &settings)?,
+            animations: RunAnimations::new(),
+            keybindings: settings.keybindings(),
+            settings,
+        })
+    }
+
+    // Advances the game by one fixed time step
+    fn tick(&mut self, ctx: &Context) -> Transition {
+        if self.simulation.is_over() {
+            return self.game_over_transition();
+        }
 
-        let mut orbs = HashMap::with_capacity(3);
-        orbs.insert('Q', quas_image);
-        orbs.insert('W', wex_image);
-        orbs.insert('E', exort_image);
+        self.ticks += 1;
+        let dt = std::time::Duration::from_secs_f32(1.0 / TICKS_PER_SECOND as f32);
+        let events = self.simulation.step(dt);
+        self.handle(ctx, events)
+    }
 
-        let mut keybindings: HashMap<KeyCode, Orb> = HashMap::with_capacity(4);
+    fn press(&mut self, ctx: &Context, orb: Option<OrbType>) -> Transition {
+        if self.simulation.is_over() {
+            return self.game_over_transition();
+        }
 
-        keybindings.insert(settings.quas_key, quas);
-        keybindings.insert(settings.wex_key, wex);
-        keybindings.insert(settings.exort_key, exort);
-        keybindings.insert(settings.invoke_key, invoke);
+        if let Some(orb) = orb {
+            self.audio.play_orb(ctx, orb);
+        }
+        self.animations
+            .orb_pressed(orb, self.simulation.buffer().len());
 
-        let input_buffer = InputBuffer::new(&settings);
+        self.replay.record(self.ticks, orb);
+        let events = self.simulation.press(orb);
+        self.handle(ctx, events)
+    }
 
-        Ok(Self {
-            game_over: false,
-            objects: Vec::new(),
-            input_buffer,
-            last_spell_time: std::time::Duration::new(0, 0),
-            speed: 0.0,
-            score: 0,
-            settings,
-            background_image,
-            keybindings,
-            orbs,
+    fn handle(&mut self, ctx: &Context, events: Vec<Event>) -> Transition {
+        self.animations.handle(&events);
+        for event in &events {
+            self.run.record(event);
+            match event {
+                Event::SpellCast { spell, .. } => self.audio.play_cast(ctx, spell),
+                Event::GameOver(end) => {
+                    self.audio.play_game_over(ctx);
+                    self.finish(end.clone());
+                }
+                _ => {}
+            }
+        }
+
+        if self.simulation.is_over() {
+            return self.game_over_transition();
+        }
+        Transition::None
+    }
+
+    // Stores the run in the run history together with its replay
+    fn finish(&mut self, end: RunEnd) {
+        self.run
+            .finish(self.simulation.score(), self.simulation.elapsed(), end);
+        if let Err(e) = self.replay.save(self.run.started_at()) {
+            println!("Could not save replay: {}", e);
+        }
+    }
+
+    fn pause(&mut self) -> Transition {
+        self.audio.pause_music();
+        Transition::Push(Overlay::Pause {
+            mode: self.simulation.mode(),
+        })
+    }
+
+    fn game_over_transition(&self) -> Transition {
+        // The lost run stays on screen while it flashes
+        if self.animations.is_failing() {
+            return Transition::None;
+        }
+
+        let mode = self.simulation.mode();
+        if !mode.is_pro() {
+            return Transition::Switch(Screen::GameOver {
+                mode,
+                score: self.simulation.score(),
+                speed: self.simulation.speed(),
+            });
+        }
+
+        let hint = self
+            .simulation
+            .hint()
+            .map(|hint| self.settings.hint_text(&hint));
+        let info = match (self.simulation.end(), hint) {
+            // A graded run is summed up no matter how it ended
+            _ if mode == GameMode::GradedPro => Some(self.simulation.grades().summary()),
+            _ if mode == GameMode::Combos => {
+                Some(combo_book::summary(self.simulation.combo_results()))
+            }
+            (Some(RunEnd::WrongKeyPressCount { pressed, .. }), Some(hint)) => {
+                Some(format!("Pressed {} keys, fewest: {}", pressed, hint))
+            }
+            (Some(RunEnd::WrongSpell { .. }), Some(hint)) => {
+                Some(format!("Wrong spell, fewest: {}", hint))
+            }
+            _ => None,
+        };
+
+        Transition::Switch(Screen::GameOverPro {
+            mode,
+            score: self.simulation.score(),
+            speed: self.simulation.speed(),
+            info,
         })
     }
 }
 
 impl GameState for MainState {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        self.renderer.apply_settings(settings);
+        self.keybindings = settings.keybindings();
+        self.settings = settings.clone();
+        self.audio.apply_settings(ctx, settings)
+    }
+
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) -> GameResult<Transition> {
+        if gained || self.simulation.is_over() {
+            return Ok(Transition::None);
+        }
+        Ok(self.pause())
+    }
+
+    // Quit or restarted from the pause overlay
+    fn abandon(&mut self) {
+        if !self.simulation.is_over() {
+            self.finish(RunEnd::Quit);
+        }
+    }
+
     fn mouse_button_up_event(
         &mut self,
-        _ctx: &mut Context,
-        _button: ggez::event::MouseButton,
+        ctx: &mut Context,
+        button: ggez::event::MouseButton,
         _x: f32,
         _y: f32,
     ) -> GameResult<Transition> {
-        Ok(Transition::None)
+        // Clicks that aren't bound to an orb don't count as a wrong press
+        let binding = Binding::from_mouse(button, ctx.keyboard.active_mods());
+        match self.keybindings.get(&binding).copied() {
+            Some(orb) => Ok(self.press(ctx, Some(orb))),
+            None => Ok(Transition::None),
+        }
     }
 
     fn update(&mut self, ctx: &mut Context) -> GameResult<Transition> {
-        self.last_spell_time += ctx.time.delta();
-        if self.last_spell_time > std::time::Duration::new(1, 0) || self.objects.is_empty() {
-            self.last_spell_time = std::time::Duration::new(0, 0);
-            self.speed += 0.5;
-            let new_spell = Spell::new(ctx, self.speed, &self.settings);
-            self.objects.push(new_spell);
-        }
+        // Paused music continues once the pause overlay is closed
+        self.audio.resume_music();
+        self.animations.advance(ctx.time.delta().as_secs_f64());
 
-        if self.game_over {
-            return Ok(Transition::GameOver { score: self.score });
-        } else {
-            for object in self.objects.iter_mut() {
-                object.position.y += object.speed;
-                if object.position.y > self.settings.window_height {
-                    self.game_over = true;
-                    return Ok(Transition::GameOver { score: self.score });
-                }
+        while ctx.time.check_update_time(TICKS_PER_SECOND) {
+            let transition = self.tick(ctx);
+            if !matches!(transition, Transition::None) {
+                return Ok(transition);
             }
         }
         Ok(Transition::None)
@@ -105,68 +236,34 @@ impl GameState for MainState {
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         let mut canvas = graphics::Canvas::from_frame(ctx, None);
-
-        canvas.draw(&self.background_image, self.settings.background_draw_param);
-        for spell in &self.objects {
-            canvas.draw(&spell.object, Vec2::new(spell.position.x, spell.position.y));
-        }
-
-        for (pos, key) in self.input_buffer.buffer.iter().enumerate() {
-            let orb_image = self.orbs.get(&key).unwrap();
-
-            let draw_param = self.input_buffer.draw_params[pos];
-
-            canvas.draw(orb_image, draw_param);
-        }
+        self.renderer
+            .draw(&mut canvas, &self.simulation, &self.animations);
         canvas.finish(ctx)?;
         Ok(())
     }
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         keycode: KeyInput,
         _repeat: bool,
     ) -> GameResult<Transition> {
-        if !self.game_over {
-            match keycode.keycode.unwrap() {
-                KeyCode::Escape => return Ok(Transition::Menu),
-
-                key => {
-                    if let Some(orb) = self.keybindings.get(&key) {
-                        let spell_cast = self.input_buffer.update_buffer(&orb);
-
-                        match spell_cast {
-                            None => return Ok(Transition::None),
-                            Some(cast) => {
-                                let mut cast = cast.clone();
-                                cast.sort_unstable();
-
-                                let mut index_to_remove = None;
-                                for (index, object) in self.objects.iter().enumerate() {
-                                    if cast == object.cast {
-                                        self.score += 1;
-                                        index_to_remove = Some(index);
-                                        break;
-                                    }
-                                }
-                                if let Some(index) = index_to_remove {
-                                    self.objects.remove(index);
-
-                                    return Ok(Transition::None);
-                                } else {
-                                    self.game_over = true;
-
-                                    return Ok(Transition::GameOver { score: self.score });
-                                }
-                            }
-                        }
-                    }
-                    Ok(Transition::None)
+        let key = match keycode.keycode {
+            Some(key) => key,
+            None => return Ok(Transition::None),
+        };
+
+        match key {
+            KeyCode::Escape if self.simulation.is_over() => Ok(self.game_over_transition()),
+            KeyCode::Escape => Ok(self.pause()),
+            key => {
+                let binding = Binding::from_key(key, keycode.mods);
+                match self.keybindings.get(&binding).copied() {
+                    // Modifiers are held down for combos, on their own they press nothing
+                    None if Binding::is_modifier_key(key) => Ok(Transition::None),
+                    orb => Ok(self.press(ctx, orb)),
                 }
             }
-        } else {
-            Ok(Transition::GameOver { score: self.score })
         }
     }
 }
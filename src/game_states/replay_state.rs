@@ -0,0 +1,199 @@
+use crate::animations::RunAnimations;
+use crate::game_renderer::GameRenderer;
+use crate::replay::Replay;
+use crate::settings::Settings;
+use crate::simulation::Simulation;
+use crate::state_machine::{GameState, Screen, Transition, TICKS_PER_SECOND};
+
+use std::time::Duration;
+
+use ggez::{
+    glam::*,
+    graphics::{self, Color},
+    input::keyboard::{KeyCode, KeyInput},
+    Context, GameResult,
+};
+
+const MIN_PLAYBACK_SPEED: f32 = 0.25;
+const MAX_PLAYBACK_SPEED: f32 = 8.0;
+
+/// Plays a recorded run back with pause, speed-up and frame-stepping.
+pub struct ReplayState {
+    replay: Replay,
+    simulation: Simulation,
+    renderer: GameRenderer,
+    animations: RunAnimations,
+    ticks: u64,
+    // Index of the next input in `replay.inputs`
+    next_input: usize,
+    paused: bool,
+    playback_speed: f32,
+    // Ticks that are due but not played yet, below one for a while at slow speeds
+    pending_ticks: f32,
+    // The player's settings with the spells the run was recorded with
+    replay_settings: Settings,
+}
+
+impl ReplayState {
+    pub fn new(ctx: &mut Context, replay: Replay, settings: &Settings) -> GameResult<Self> {
+        let mut replay_settings = settings.clone();
+        replay_settings.spell_book = replay.spell_book.clone();
+        replay_settings.combo_book = replay.combo_book.clone();
+
+        // Spells spawn relative to the window size, so the run plays out in a field of
+        // the size it was recorded in, the renderer scales it to the window
+        let simulation = Simulation::new(
+            replay.mode,
+            replay.seed,
+            replay.spawner.clone(),
+            replay.spell_book.clone(),
+            replay.combo_book.clone(),
+            replay.window_width,
+            replay.window_height,
+        );
+        let simulation = match replay.time_limit {
+            Some(seconds) => simulation.with_time_limit(Duration::from_secs(seconds.into())),
+            None => simulation,
+        };
+
+        Ok(Self {
+            renderer: GameRenderer::new(ctx, &replay_settings)?,
+            animations: RunAnimations::new(),
+            replay,
+            simulation,
+            ticks: 0,
+            next_input: 0,
+            paused: false,
+            playback_speed: 1.0,
+            pending_ticks: 0.0,
+            replay_settings,
+        })
+    }
+
+    // Feeds the inputs recorded before the current tick and plays the tick,
+    // the same way the game states do
+    fn step(&mut self) {
+        while let Some(input) = self.replay.inputs.get(self.next_input) {
+            if input.tick > self.ticks {
+                break;
+            }
+            self.next_input += 1;
+            self.animations
+                .orb_pressed(input.orb, self.simulation.buffer().len());
+            let events = self.simulation.press(input.orb);
+            self.animations.handle(&events);
+        }
+
+        if self.simulation.is_over() {
+            return;
+        }
+
+        self.ticks += 1;
+        let dt = Duration::from_secs_f32(1.0 / TICKS_PER_SECOND as f32);
+        let events = self.simulation.step(dt);
+        self.animations.handle(&events);
+    }
+
+    fn status(&self) -> String {
+        let seconds = self.ticks as f32 / TICKS_PER_SECOND as f32;
+        let state = if self.simulation.is_over() {
+            "Finished"
+        } else if self.paused {
+            "Paused"
+        } else {
+            "Playing"
+        };
+
+        format!(
+            "Replay {:.2}s  x{}  {}\nSPACE pause  RIGHT step  UP/DOWN speed  ESC menu",
+            seconds, self.playback_speed, state
+        )
+    }
+}
+
+impl GameState for ReplayState {
+    fn settings_changed(&mut self, _ctx: &mut Context, settings: &Settings) -> GameResult {
+        self.replay_settings = self
+            .replay_settings
+            .with_window_size(settings.window_width, settings.window_height);
+        self.renderer.apply_settings(&self.replay_settings);
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<Transition> {
+        // Time keeps being consumed while paused so unpausing does not fast forward
+        while ctx.time.check_update_time(TICKS_PER_SECOND) {
+            if !self.paused && !self.simulation.is_over() {
+                self.pending_ticks += self.playback_speed;
+            }
+        }
+
+        while self.pending_ticks >= 1.0 {
+            self.pending_ticks -= 1.0;
+            self.step();
+        }
+        self.animations.advance(ctx.time.delta().as_secs_f64());
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut canvas = graphics::Canvas::from_frame(ctx, None);
+        self.renderer
+            .draw(&mut canvas, &self.simulation, &self.animations);
+
+        let status = graphics::Text::new(self.status())
+            .set_scale(self.replay_settings.font_size * 0.6)
+            .clone();
+        let dest = Vec2::new(
+            self.replay_settings.font_size * 0.5,
+            self.replay_settings.font_size * 0.5,
+        );
+        canvas.draw(
+            &status,
+            graphics::DrawParam::new().dest(dest).color(Color::WHITE),
+        );
+
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: ggez::event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyInput,
+        _repeat: bool,
+    ) -> GameResult<Transition> {
+        let key = match keycode.keycode {
+            Some(key) => key,
+            None => return Ok(Transition::None),
+        };
+
+        match key {
+            KeyCode::Escape => return Ok(Transition::Switch(Screen::Menu)),
+            KeyCode::Space => {
+                self.paused = !self.paused;
+                self.pending_ticks = 0.0;
+            }
+            // Frame-stepping only makes sense while paused
+            KeyCode::Right if self.paused => self.step(),
+            KeyCode::Up => {
+                self.playback_speed = (self.playback_speed * 2.0).min(MAX_PLAYBACK_SPEED);
+            }
+            KeyCode::Down => {
+                self.playback_speed = (self.playback_speed / 2.0).max(MIN_PLAYBACK_SPEED);
+            }
+            _ => {}
+        }
+        Ok(Transition::None)
+    }
+}
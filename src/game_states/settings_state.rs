@@ -1,75 +1,424 @@
-use crate::assets::{BACKGROUND_IMAGE, EXORT, QUAS, WEX};
-use crate::settings::Settings;
-use crate::state_machine::{GameState, Transition};
+use crate::assets::{BACKGROUND_IMAGE, EXORT, INVOKE, QUAS, WEX};
+use crate::audio::MAX_VOLUME;
+use crate::bindings::Binding;
+use crate::buttons::MenuButton;
+use crate::settings::{Settings, TIME_LIMITS};
+use crate::state_machine::{GameState, Screen, Transition};
 
 use ggez::{
     glam::*,
-    graphics::{self},
-    input::keyboard::KeyInput,
+    graphics::{self, Color, Drawable, Rect},
+    input::keyboard::{KeyCode, KeyInput},
     Context, GameResult,
 };
 
+// Window sizes to choose from, a size set by hand in settings.toml stays until another one is picked
+const WINDOW_SIZES: [(f32, f32); 7] = [
+    (800.0, 600.0),
+    (1024.0, 768.0),
+    (1024.0, 1024.0),
+    (1280.0, 720.0),
+    (1280.0, 1024.0),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+];
+
+// The size `step` presets away from `current`. A size set by hand goes to the nearest preset
+// in that direction, the presets are sorted by width and then height
+fn next_window_size(current: (f32, f32), step: isize) -> (f32, f32) {
+    let count = WINDOW_SIZES.len() as isize;
+    let index = match WINDOW_SIZES.iter().position(|size| *size == current) {
+        Some(index) => (index as isize + step).rem_euclid(count),
+        None if step < 0 => WINDOW_SIZES
+            .iter()
+            .rposition(|size| *size < current)
+            .map_or(count - 1, |index| index as isize),
+        None => WINDOW_SIZES
+            .iter()
+            .position(|size| *size > current)
+            .map_or(0, |index| index as isize),
+    };
+    WINDOW_SIZES[index as usize]
+}
+
+// Percent a volume changes with every press
+const VOLUME_STEP: i32 = 10;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Row {
+    Quas,
+    Wex,
+    Exort,
+    Invoke,
+    FirstSlot,
+    SecondSlot,
+    MasterVolume,
+    EffectsVolume,
+    MusicVolume,
+    WindowSize,
+    AdaptiveSpawning,
+    TimeLimit,
+    HudScore,
+    HudTime,
+    HudSpeed,
+    HudStreak,
+    HudPreview,
+    HudHint,
+    Save,
+    Back,
+}
+
+// The first two columns of rows, Save and Back are under them
+const COLUMN_ROWS: usize = 9;
+const ROWS: [Row; 20] = [
+    Row::Quas,
+    Row::Wex,
+    Row::Exort,
+    Row::Invoke,
+    Row::FirstSlot,
+    Row::SecondSlot,
+    Row::MasterVolume,
+    Row::EffectsVolume,
+    Row::MusicVolume,
+    Row::WindowSize,
+    Row::AdaptiveSpawning,
+    Row::TimeLimit,
+    Row::HudScore,
+    Row::HudTime,
+    Row::HudSpeed,
+    Row::HudStreak,
+    Row::HudPreview,
+    Row::HudHint,
+    Row::Save,
+    Row::Back,
+];
+
 pub struct SettingsState {
+    // The window is drawn with the settings that are in use until the edited ones are saved
     settings: Settings,
+    edited: Settings,
     background_image: graphics::Image,
-    quas: graphics::Image,
-    quas_draw_param: graphics::DrawParam,
-    wex: graphics::Image,
-    wex_draw_param: graphics::DrawParam,
-    exort: graphics::Image,
-    exort_draw_param: graphics::DrawParam,
+    title: MenuButton,
+    buttons: Vec<MenuButton>,
+    selected: usize,
+    // Binding row that waits for the player to press a key
+    waiting_for_key: Option<Row>,
+    message: Option<(graphics::Text, Color)>,
+    orbs: Vec<(graphics::Image, graphics::DrawParam)>,
+    // Opened from the pause overlay, going back returns to the paused run
+    overlay: bool,
 }
 
 impl SettingsState {
-    pub fn _new(ctx: &mut Context, settings: &Settings) -> GameResult<Self> {
+    pub fn new(ctx: &mut Context, settings: &Settings) -> GameResult<Self> {
         let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
-        let quas = graphics::Image::from_bytes(ctx, QUAS)?;
-        let wex = graphics::Image::from_bytes(ctx, WEX)?;
-        let exort = graphics::Image::from_bytes(ctx, EXORT)?;
 
-        let (quas_draw_param, wex_draw_param, exort_draw_param) =
-            Self::_calculate_orb_positions(settings);
+        let title = MenuButton::new(
+            ctx,
+            "Settings",
+            "",
+            settings,
+            settings.window_width / 2.0,
+            settings.window_height / 12.0,
+            Some(2.0),
+        )?;
 
-        Ok(Self {
+        // Orb icons next to the rows that bind them
+        let mut orbs = Vec::with_capacity(4);
+        for (row, image) in [QUAS, WEX, EXORT, INVOKE].into_iter().enumerate() {
+            let image = graphics::Image::from_bytes(ctx, image)?;
+            let size = settings.window_height / 20.0;
+            let scale = size / image.width() as f32;
+            let position = Self::row_position(settings, row);
+            let dest = Vec2::new(
+                position.x - settings.window_width / 6.0 - size * 1.5,
+                position.y,
+            );
+            let draw_param = graphics::DrawParam::new()
+                .dest(dest)
+                .scale(Vec2::new(scale, scale));
+            orbs.push((image, draw_param));
+        }
+
+        let mut state = Self {
             settings: settings.clone(),
+            edited: settings.clone(),
             background_image,
-            quas,
-            quas_draw_param,
-            wex,
-            wex_draw_param,
-            exort,
-            exort_draw_param,
+            title,
+            buttons: Vec::new(),
+            selected: 0,
+            waiting_for_key: None,
+            message: None,
+            orbs,
+            overlay: false,
+        };
+        state.build_buttons(ctx)?;
+
+        Ok(state)
+    }
+
+    pub fn overlay(ctx: &mut Context, settings: &Settings) -> GameResult<Self> {
+        let mut state = Self::new(ctx, settings)?;
+        state.overlay = true;
+        Ok(state)
+    }
+
+    fn back(&self) -> Transition {
+        if self.overlay {
+            Transition::Pop
+        } else {
+            Transition::Switch(Screen::Menu)
+        }
+    }
+
+    // Center of the row's button at the top
+    fn row_position(settings: &Settings, row: usize) -> Vec2 {
+        let (column, line) = if row < COLUMN_ROWS * 2 {
+            (row / COLUMN_ROWS, row % COLUMN_ROWS)
+        } else {
+            (row - COLUMN_ROWS * 2, COLUMN_ROWS + 1)
+        };
+
+        Vec2::new(
+            settings.window_width * (0.3 + 0.4 * column as f32),
+            settings.window_height * (0.18 + 0.065 * line as f32),
+        )
+    }
+
+    // Buttons show the current values, so they are rebuilt after every change
+    fn build_buttons(&mut self, ctx: &mut Context) -> GameResult {
+        let mut buttons = Vec::with_capacity(ROWS.len());
+
+        for (index, row) in ROWS.into_iter().enumerate() {
+            let (text, hint) = self.row_text(row);
+            let position = Self::row_position(&self.settings, index);
+            let button = MenuButton::new(
+                ctx,
+                &text,
+                hint,
+                &self.settings,
+                position.x,
+                position.y,
+                Some(3.0),
+            )?;
+            buttons.push(button);
+        }
+
+        self.buttons = buttons;
+        Ok(())
+    }
+
+    fn row_text(&self, row: Row) -> (String, &'static str) {
+        let binding = |name: &str, binding: Binding| {
+            if self.waiting_for_key == Some(row) {
+                format!("{}: press a key or mouse button", name)
+            } else {
+                format!("{}: {}", name, binding)
+            }
+        };
+
+        match row {
+            Row::Quas => (binding("Quas", self.edited.quas_key), "RET"),
+            Row::Wex => (binding("Wex", self.edited.wex_key), "RET"),
+            Row::Exort => (binding("Exort", self.edited.exort_key), "RET"),
+            Row::Invoke => (binding("Invoke", self.edited.invoke_key), "RET"),
+            Row::FirstSlot => (binding("First slot", self.edited.first_slot_key), "RET"),
+            Row::SecondSlot => (binding("Second slot", self.edited.second_slot_key), "RET"),
+            Row::MasterVolume => (
+                format!("Master volume: {}%", self.edited.audio.master),
+                "LEFT/RIGHT",
+            ),
+            Row::EffectsVolume => (
+                format!("Effects volume: {}%", self.edited.audio.effects),
+                "LEFT/RIGHT",
+            ),
+            Row::MusicVolume => (
+                format!("Music volume: {}%", self.edited.audio.music),
+                "LEFT/RIGHT",
+            ),
+            Row::WindowSize => (
+                format!(
+                    "Window: {}x{}",
+                    self.edited.window_width, self.edited.window_height
+                ),
+                "LEFT/RIGHT",
+            ),
+            Row::AdaptiveSpawning => (
+                Self::toggle_text("Adaptive spawning", self.edited.adaptive_spawning),
+                "RET",
+            ),
+            Row::TimeLimit => (
+                format!("Time attack: {}s", self.edited.time_limit),
+                "LEFT/RIGHT",
+            ),
+            Row::HudScore => (
+                Self::toggle_text("Show score", self.edited.hud.score),
+                "RET",
+            ),
+            Row::HudTime => (Self::toggle_text("Show time", self.edited.hud.time), "RET"),
+            Row::HudSpeed => (
+                Self::toggle_text("Show speed", self.edited.hud.speed),
+                "RET",
+            ),
+            Row::HudStreak => (
+                Self::toggle_text("Show streak", self.edited.hud.streak),
+                "RET",
+            ),
+            Row::HudPreview => (
+                Self::toggle_text("Show spell preview", self.edited.hud.preview),
+                "RET",
+            ),
+            Row::HudHint => (
+                Self::toggle_text("Show Pro mode hint", self.edited.hud.hint),
+                "RET",
+            ),
+            Row::Save => (String::from("Save"), "RET"),
+            Row::Back => (String::from("Back"), "ESC"),
+        }
+    }
+
+    fn button_at(&self, x: f32, y: f32) -> Option<usize> {
+        let cursor_location = Vec2::new(x, y);
+        self.buttons.iter().position(|button| {
+            Rect::new(
+                button.position.x,
+                button.position.y,
+                button.dimensions.w,
+                button.dimensions.h,
+            )
+            .contains(cursor_location)
         })
     }
 
-    fn _calculate_orb_positions(
-        settings: &Settings,
-    ) -> (
-        graphics::DrawParam,
-        graphics::DrawParam,
-        graphics::DrawParam,
-    ) {
-        // TODO: remove hardcoded image sizes
-        let offset = settings.window_width / 50.0;
-        let box_width = 77.0 * 3.0 + 50.0 * 2.0;
-        let orb_position_h = settings.window_height / 2.0 - 77.0 / 2.0;
-        let quas_position_w = settings.window_width / 2.0 - box_width / 2.0;
-        let wex_position_w = quas_position_w + 77.0 + offset;
-        let exort_position_w = wex_position_w + 77.0 + offset;
+    fn toggle_text(name: &str, value: bool) -> String {
+        format!("{}: {}", name, if value { "On" } else { "Off" })
+    }
 
-        let quas_position = Vec2::new(quas_position_w, orb_position_h);
-        let wex_position = Vec2::new(wex_position_w, orb_position_h);
-        let exort_position = Vec2::new(exort_position_w, orb_position_h);
+    fn show_message(&mut self, text: &str, color: Color) {
+        let text = graphics::Text::new(text)
+            .set_scale(self.settings.font_size * 0.8)
+            .clone();
+        self.message = Some((text, color));
+    }
+
+    fn activate(&mut self, ctx: &mut Context, row: Row) -> GameResult<Transition> {
+        self.message = None;
+
+        match row {
+            Row::Quas | Row::Wex | Row::Exort | Row::Invoke | Row::FirstSlot | Row::SecondSlot => {
+                self.waiting_for_key = Some(row);
+            }
+            Row::MasterVolume | Row::EffectsVolume | Row::MusicVolume => {
+                // Wraps around, so the mouse alone can set any volume
+                if self.volume(row) == Some(MAX_VOLUME) {
+                    self.change_volume(row, -(MAX_VOLUME as i32));
+                } else {
+                    self.change_volume(row, VOLUME_STEP);
+                }
+            }
+            Row::WindowSize => self.cycle_window_size(1),
+            Row::AdaptiveSpawning => {
+                self.edited.adaptive_spawning = !self.edited.adaptive_spawning;
+            }
+            Row::TimeLimit => self.cycle_time_limit(1),
+            Row::HudScore => self.edited.hud.score = !self.edited.hud.score,
+            Row::HudTime => self.edited.hud.time = !self.edited.hud.time,
+            Row::HudSpeed => self.edited.hud.speed = !self.edited.hud.speed,
+            Row::HudStreak => self.edited.hud.streak = !self.edited.hud.streak,
+            Row::HudPreview => self.edited.hud.preview = !self.edited.hud.preview,
+            Row::HudHint => self.edited.hud.hint = !self.edited.hud.hint,
+            Row::Save => return Ok(self.save()),
+            Row::Back => return Ok(self.back()),
+        }
+
+        self.build_buttons(ctx)?;
+        Ok(Transition::None)
+    }
 
-        let quas_draw_param = graphics::DrawParam::new().dest(quas_position);
-        let wex_draw_param = graphics::DrawParam::new().dest(wex_position);
-        let exort_draw_param = graphics::DrawParam::new().dest(exort_position);
+    fn volume(&self, row: Row) -> Option<u32> {
+        match row {
+            Row::MasterVolume => Some(self.edited.audio.master),
+            Row::EffectsVolume => Some(self.edited.audio.effects),
+            Row::MusicVolume => Some(self.edited.audio.music),
+            _ => None,
+        }
+    }
 
-        (quas_draw_param, wex_draw_param, exort_draw_param)
+    fn change_volume(&mut self, row: Row, step: i32) {
+        let audio = &mut self.edited.audio;
+        let volume = match row {
+            Row::MasterVolume => &mut audio.master,
+            Row::EffectsVolume => &mut audio.effects,
+            Row::MusicVolume => &mut audio.music,
+            _ => return,
+        };
+        *volume = (*volume as i32 + step).clamp(0, MAX_VOLUME as i32) as u32;
+    }
+
+    fn cycle_window_size(&mut self, step: isize) {
+        let current = (self.edited.window_width, self.edited.window_height);
+        let (width, height) = next_window_size(current, step);
+        self.edited = self.edited.with_window_size(width, height);
+    }
+
+    fn cycle_time_limit(&mut self, step: isize) {
+        let count = TIME_LIMITS.len() as isize;
+        let index = match TIME_LIMITS
+            .iter()
+            .position(|limit| *limit == self.edited.time_limit)
+        {
+            Some(index) => (index as isize + step).rem_euclid(count),
+            None => 0,
+        };
+        self.edited.time_limit = TIME_LIMITS[index as usize];
+    }
+
+    fn bind(&mut self, ctx: &mut Context, binding: Binding) -> GameResult {
+        if let Some(row) = self.waiting_for_key.take() {
+            match row {
+                Row::Quas => self.edited.quas_key = binding,
+                Row::Wex => self.edited.wex_key = binding,
+                Row::Exort => self.edited.exort_key = binding,
+                Row::Invoke => self.edited.invoke_key = binding,
+                Row::FirstSlot => self.edited.first_slot_key = binding,
+                Row::SecondSlot => self.edited.second_slot_key = binding,
+                _ => {}
+            }
+        }
+        self.build_buttons(ctx)
+    }
+
+    fn save(&mut self) -> Transition {
+        if let Some(conflict) = self.edited.binding_conflict() {
+            self.show_message(&conflict, Color::from_rgb(255, 80, 80));
+            return Transition::None;
+        }
+
+        if let Err(e) = self.edited.save() {
+            self.show_message(
+                &format!("Could not save settings: {}", e),
+                Color::from_rgb(255, 80, 80),
+            );
+            return Transition::None;
+        }
+
+        Transition::ApplySettings(Box::new(self.edited.clone()))
     }
 }
 
 impl GameState for SettingsState {
+    // Starts over with the new settings, edits that aren't saved yet are kept
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        let mut state = Self::new(ctx, settings)?;
+        state.edited = self.edited.clone();
+        state.selected = self.selected;
+        state.waiting_for_key = self.waiting_for_key;
+        state.overlay = self.overlay;
+        state.build_buttons(ctx)?;
+        *self = state;
+        Ok(())
+    }
+
     fn update(&mut self, _ctx: &mut Context) -> GameResult<Transition> {
         Ok(Transition::None)
     }
@@ -77,9 +426,38 @@ impl GameState for SettingsState {
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         let mut canvas = graphics::Canvas::from_frame(ctx, None);
         canvas.draw(&self.background_image, self.settings.background_draw_param);
-        canvas.draw(&self.quas, self.quas_draw_param);
-        canvas.draw(&self.wex, self.wex_draw_param);
-        canvas.draw(&self.exort, self.exort_draw_param);
+
+        canvas.draw(&self.title.background, self.title.draw_param);
+        canvas.draw(&self.title.text, self.title.text_draw_param);
+
+        for (index, button) in self.buttons.iter().enumerate() {
+            let background_color = if index == self.selected {
+                Color::from_rgb(255, 215, 120)
+            } else {
+                Color::WHITE
+            };
+            canvas.draw(
+                &button.background,
+                button.draw_param.color(background_color),
+            );
+            canvas.draw(&button.text, button.text_draw_param);
+            canvas.draw(&button.hint, button.hint_draw_param);
+        }
+
+        for (image, draw_param) in &self.orbs {
+            canvas.draw(image, *draw_param);
+        }
+
+        if let Some((text, color)) = &self.message {
+            let dimensions = text
+                .dimensions(ctx)
+                .expect("Text Drawable always has dimensions");
+            let dest = Vec2::new(
+                (self.settings.window_width - dimensions.w) / 2.0,
+                self.settings.window_height * 0.9,
+            );
+            canvas.draw(text, graphics::DrawParam::new().dest(dest).color(*color));
+        }
 
         canvas.finish(ctx)?;
 
@@ -87,21 +465,117 @@ impl GameState for SettingsState {
     }
 
     fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        button: ggez::event::MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult<Transition> {
+        if self.waiting_for_key.is_some() {
+            let binding = Binding::from_mouse(button, ctx.keyboard.active_mods());
+            self.bind(ctx, binding)?;
+            return Ok(Transition::None);
+        }
+
+        if button != ggez::event::MouseButton::Left {
+            return Ok(Transition::None);
+        }
+
+        if let Some(index) = self.button_at(x, y) {
+            self.selected = index;
+            return self.activate(ctx, ROWS[index]);
+        }
+
+        Ok(Transition::None)
+    }
+
+    // The row under the cursor is selected, unless a binding is waiting for its key
+    fn mouse_motion_event(
         &mut self,
         _ctx: &mut Context,
-        _button: ggez::event::MouseButton,
-        _x: f32,
-        _y: f32,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
     ) -> GameResult<Transition> {
+        if self.waiting_for_key.is_none() {
+            if let Some(index) = self.button_at(x, y) {
+                self.selected = index;
+            }
+        }
         Ok(Transition::None)
     }
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
-        _keycode: KeyInput,
+        ctx: &mut Context,
+        keycode: KeyInput,
         _repeat: bool,
     ) -> GameResult<Transition> {
+        let key = match keycode.keycode {
+            Some(key) => key,
+            None => return Ok(Transition::None),
+        };
+
+        if self.waiting_for_key.is_some() {
+            match key {
+                KeyCode::Escape => {
+                    self.waiting_for_key = None;
+                    self.build_buttons(ctx)?;
+                }
+                // Modifiers wait for the key they are held with, e.g. Ctrl+Q
+                key if Binding::is_modifier_key(key) => {}
+                key => self.bind(ctx, Binding::from_key(key, keycode.mods))?,
+            }
+            return Ok(Transition::None);
+        }
+
+        match key {
+            KeyCode::Up => self.selected = (self.selected + ROWS.len() - 1) % ROWS.len(),
+            KeyCode::Down => self.selected = (self.selected + 1) % ROWS.len(),
+            KeyCode::Left | KeyCode::Right if ROWS[self.selected] == Row::WindowSize => {
+                self.cycle_window_size(if key == KeyCode::Left { -1 } else { 1 });
+                self.build_buttons(ctx)?;
+            }
+            KeyCode::Left | KeyCode::Right if ROWS[self.selected] == Row::TimeLimit => {
+                self.cycle_time_limit(if key == KeyCode::Left { -1 } else { 1 });
+                self.build_buttons(ctx)?;
+            }
+            KeyCode::Left | KeyCode::Right if self.volume(ROWS[self.selected]).is_some() => {
+                let step = if key == KeyCode::Left {
+                    -VOLUME_STEP
+                } else {
+                    VOLUME_STEP
+                };
+                self.change_volume(ROWS[self.selected], step);
+                self.build_buttons(ctx)?;
+            }
+            KeyCode::Return => return self.activate(ctx, ROWS[self.selected]),
+            KeyCode::Escape => return Ok(self.back()),
+            _ => {}
+        }
+
         Ok(Transition::None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_window_size() {
+        assert_eq!(next_window_size((1024.0, 768.0), 1), (1024.0, 1024.0));
+        assert_eq!(next_window_size((800.0, 600.0), -1), (1920.0, 1080.0));
+
+        // Set by hand in settings.toml, between 1280x1024 and 1600x900
+        assert_eq!(next_window_size((1440.0, 900.0), -1), (1280.0, 1024.0));
+        assert_eq!(next_window_size((1440.0, 900.0), 1), (1600.0, 900.0));
+        assert_eq!(next_window_size((1280.0, 800.0), -1), (1280.0, 720.0));
+        assert_eq!(next_window_size((1280.0, 800.0), 1), (1280.0, 1024.0));
+
+        // Beyond the presets it wraps around like the presets do
+        assert_eq!(next_window_size((2560.0, 1440.0), 1), (800.0, 600.0));
+        assert_eq!(next_window_size((640.0, 480.0), -1), (1920.0, 1080.0));
+    }
+}
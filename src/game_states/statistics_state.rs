@@ -0,0 +1,214 @@
+use crate::assets::BACKGROUND_IMAGE;
+use crate::buttons::MenuButton;
+use crate::game_renderer::GameRenderer;
+use crate::run_history::RunRecord;
+use crate::settings::Settings;
+use crate::state_machine::{GameState, Screen, Transition};
+use crate::statistics::Statistics;
+
+use ggez::{
+    glam::*,
+    graphics::{self, Color, DrawParam, Rect},
+    input::keyboard::{KeyCode, KeyInput},
+    Context, GameResult,
+};
+
+// Left edge of every column as a fraction of the window width
+const COLUMNS: [f32; 4] = [0.2, 0.45, 0.62, 0.77];
+
+pub struct StatisticsState {
+    background_image: graphics::Image,
+    settings: Settings,
+    buttons: Vec<MenuButton>,
+    table_background: graphics::Mesh,
+    table_background_draw_param: DrawParam,
+    icons: Vec<(graphics::Image, DrawParam)>,
+    cells: Vec<(graphics::Text, DrawParam)>,
+}
+
+impl StatisticsState {
+    pub fn new(ctx: &mut Context, settings: &Settings) -> GameResult<Self> {
+        let statistics = Statistics::from_runs(&RunRecord::load_all());
+
+        let mut buttons = Vec::new();
+
+        let title_button_x = settings.window_width / 2.0;
+        let title_button_y = settings.window_height / 12.0;
+        let title_button = MenuButton::new(
+            ctx,
+            &format!("Statistics of {} runs", statistics.runs),
+            "",
+            settings,
+            title_button_x,
+            title_button_y,
+            Some(2.0),
+        )?;
+
+        let go_to_menu_button_x = settings.window_width / 2.0;
+        let go_to_menu_button_y = settings.window_height * 0.85;
+        let go_to_menu_button = MenuButton::new(
+            ctx,
+            "Menu",
+            "ESC",
+            settings,
+            go_to_menu_button_x,
+            go_to_menu_button_y,
+            None,
+        )?;
+
+        buttons.push(title_button);
+        buttons.push(go_to_menu_button);
+
+        let font_size = settings.font_size * 0.8;
+        let row_height = font_size * 1.8;
+        let top_y = settings.window_height * 0.2;
+
+        let mut cells = Vec::new();
+        let header = ["Spell", "Avg. cast time", "Miss rate", "Runs ended"];
+        for (value, column) in header.into_iter().zip(COLUMNS) {
+            let text = graphics::Text::new(value).set_scale(font_size).clone();
+            let dest = Vec2::new(settings.window_width * column, top_y);
+            let color = Color::from_rgb(200, 200, 200);
+            cells.push((text, DrawParam::new().dest(dest).color(color)));
+        }
+
+        // The spell that ended the most runs is the one to practice
+        let deadliest = statistics.deadliest();
+
+        let spells = settings.spell_book.spells();
+        let mut icons = Vec::with_capacity(spells.len());
+        for (row, spell) in spells.iter().enumerate() {
+            let stats = statistics.spell(&spell.id);
+            let y = top_y + row_height * (row + 1) as f32;
+
+            let color = if Some(&spell.id) == deadliest.as_ref() {
+                Color::from_rgb(255, 80, 80)
+            } else {
+                Color::WHITE
+            };
+
+            let values = [
+                spell.name.clone(),
+                stats
+                    .average_cast_time()
+                    .map_or(String::from("-"), |time| format!("{:.2}s", time)),
+                stats
+                    .miss_rate()
+                    .map_or(String::from("-"), |rate| format!("{:.0}%", rate * 100.0)),
+                format!("{}", stats.kills),
+            ];
+            for (value, column) in values.into_iter().zip(COLUMNS) {
+                let text = graphics::Text::new(value).set_scale(font_size).clone();
+                let dest = Vec2::new(settings.window_width * column, y);
+                cells.push((text, DrawParam::new().dest(dest).color(color)));
+            }
+
+            // Scale the icons down to the row height
+            let icon = GameRenderer::spell_icon(ctx, spell)?;
+            let icon_scale = (row_height * 0.9) / icon.width() as f32;
+            let icon_dest = Vec2::new(
+                settings.window_width * COLUMNS[0] - row_height,
+                y - (row_height - font_size) / 2.0,
+            );
+            let icon_draw_param = DrawParam::new()
+                .dest(icon_dest)
+                .scale(Vec2::new(icon_scale, icon_scale));
+            icons.push((icon, icon_draw_param));
+        }
+
+        let padding = font_size * 0.5;
+        let table_x = settings.window_width * COLUMNS[0] - row_height - padding;
+        let table_width = settings.window_width - table_x * 2.0;
+        let table_height = row_height * (spells.len() + 1) as f32 + padding * 2.0;
+        let table_background = graphics::Mesh::new_rounded_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(0.0, 0.0, table_width, table_height),
+            10.0,
+            Color::from_rgba(0, 0, 0, 180),
+        )?;
+        let table_background_draw_param =
+            DrawParam::new().dest(Vec2::new(table_x, top_y - padding));
+
+        let background_image = graphics::Image::from_bytes(ctx, BACKGROUND_IMAGE)?;
+
+        Ok(Self {
+            background_image,
+            settings: settings.clone(),
+            buttons,
+            table_background,
+            table_background_draw_param,
+            icons,
+            cells,
+        })
+    }
+}
+
+impl GameState for StatisticsState {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        *self = Self::new(ctx, settings)?;
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+        canvas.draw(&self.background_image, self.settings.background_draw_param);
+
+        for button in &self.buttons {
+            canvas.draw(&button.background, button.draw_param);
+            canvas.draw(&button.text, button.text_draw_param);
+            canvas.draw(&button.hint, button.hint_draw_param);
+        }
+
+        canvas.draw(&self.table_background, self.table_background_draw_param);
+        for (icon, draw_param) in &self.icons {
+            canvas.draw(icon, *draw_param);
+        }
+        for (text, draw_param) in &self.cells {
+            canvas.draw(text, *draw_param);
+        }
+
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: ggez::event::MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult<Transition> {
+        if button == ggez::event::MouseButton::Left {
+            let cursor_location = Vec2::new(x, y);
+            let go_to_menu_rect = Rect::new(
+                self.buttons[1].position.x,
+                self.buttons[1].position.y,
+                self.buttons[1].dimensions.w,
+                self.buttons[1].dimensions.h,
+            );
+
+            if go_to_menu_rect.contains(cursor_location) {
+                return Ok(Transition::Switch(Screen::Menu));
+            }
+        }
+
+        Ok(Transition::None)
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyInput,
+        _repeat: bool,
+    ) -> GameResult<Transition> {
+        match keycode.keycode {
+            Some(KeyCode::Escape) => Ok(Transition::Switch(Screen::Menu)),
+            _ => Ok(Transition::None),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::animations::RunAnimations;
+use crate::audio::Audio;
+use crate::bindings::Binding;
+use crate::game_renderer::GameRenderer;
+use crate::highscores::GameMode;
+use crate::orbs::OrbType;
+use crate::replay::Replay;
+use crate::run_history::{RunEnd, RunRecorder};
+use crate::settings::Settings;
+use crate::simulation::{Event, Simulation};
+use crate::spawner::Spawner;
+use crate::state_machine::{GameState, Overlay, Screen, Transition, TICKS_PER_SECOND};
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ggez::{
+    graphics::{self},
+    input::keyboard::{KeyCode, KeyInput},
+    Context, GameResult,
+};
+
+/// As many spells as possible before the time limit of the settings runs out.
+pub struct TimeAttackState {
+    ticks: u64,
+    simulation: Simulation,
+    run: RunRecorder,
+    replay: Replay,
+    renderer: GameRenderer,
+    audio: Audio,
+    animations: RunAnimations,
+    keybindings: HashMap<Binding, OrbType>,
+}
+
+impl TimeAttackState {
+    pub fn new(settings: Settings, ctx: &mut Context, seed: u64) -> GameResult<Self> {
+        let mode = GameMode::TimeAttack;
+        let spawner = Spawner::new(settings.adaptive_spawning, &settings.spell_book);
+
+        let simulation = Simulation::new(
+            mode,
+            seed,
+            spawner.clone(),
+            settings.spell_book.clone(),
+            settings.combo_book.clone(),
+            settings.window_width,
+            settings.window_height,
+        )
+        .with_time_limit(Duration::from_secs(settings.time_limit.into()));
+        let replay = Replay::new(mode, seed, &settings, spawner);
+
+        Ok(Self {
+            ticks: 0,
+            simulation,
+            run: RunRecorder::new(mode, seed),
+            replay,
+            renderer: GameRenderer::new(ctx, &settings)?,
+            audio: Audio::new(ctx, &settings)?,
+            animations: RunAnimations::new(),
+            keybindings: settings.keybindings(),
+        })
+    }
+
+    fn tick(&mut self, ctx: &Context) -> Transition {
+        if self.simulation.is_over() {
+            return self.game_over_transition();
+        }
+
+        self.ticks += 1;
+        let dt = Duration::from_secs_f32(1.0 / TICKS_PER_SECOND as f32);
+        let events = self.simulation.step(dt);
+        self.handle(ctx, events)
+    }
+
+    fn press(&mut self, ctx: &Context, orb: Option<OrbType>) -> Transition {
+        if self.simulation.is_over() {
+            return self.game_over_transition();
+        }
+
+        if let Some(orb) = orb {
+            self.audio.play_orb(ctx, orb);
+        }
+        self.animations
+            .orb_pressed(orb, self.simulation.buffer().len());
+
+        self.replay.record(self.ticks, orb);
+        let events = self.simulation.press(orb);
+        self.handle(ctx, events)
+    }
+
+    fn handle(&mut self, ctx: &Context, events: Vec<Event>) -> Transition {
+        self.animations.handle(&events);
+        for event in &events {
+            self.run.record(event);
+            match event {
+                Event::SpellCast { spell, .. } => self.audio.play_cast(ctx, spell),
+                Event::GameOver(end) => {
+                    self.audio.play_game_over(ctx);
+                    self.finish(end.clone());
+                }
+                _ => {}
+            }
+        }
+
+        if self.simulation.is_over() {
+            return self.game_over_transition();
+        }
+        Transition::None
+    }
+
+    fn finish(&mut self, end: RunEnd) {
+        self.run
+            .finish(self.simulation.score(), self.simulation.elapsed(), end);
+        if let Err(e) = self.replay.save(self.run.started_at()) {
+            println!("Could not save replay: {}", e);
+        }
+    }
+
+    fn pause(&mut self) -> Transition {
+        self.audio.pause_music();
+        Transition::Push(Overlay::Pause {
+            mode: GameMode::TimeAttack,
+        })
+    }
+
+    fn game_over_transition(&self) -> Transition {
+        Transition::Switch(Screen::GameOverTimeAttack {
+            result: self.simulation.time_attack_result(),
+        })
+    }
+}
+
+impl GameState for TimeAttackState {
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult {
+        self.renderer.apply_settings(settings);
+        self.keybindings = settings.keybindings();
+        self.audio.apply_settings(ctx, settings)
+    }
+
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) -> GameResult<Transition> {
+        if gained || self.simulation.is_over() {
+            return Ok(Transition::None);
+        }
+        Ok(self.pause())
+    }
+
+    fn abandon(&mut self) {
+        if !self.simulation.is_over() {
+            self.finish(RunEnd::Quit);
+        }
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        button: ggez::event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> GameResult<Transition> {
+        let binding = Binding::from_mouse(button, ctx.keyboard.active_mods());
+        match self.keybindings.get(&binding).copied() {
+            Some(orb) => Ok(self.press(ctx, Some(orb))),
+            None => Ok(Transition::None),
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<Transition> {
+        self.audio.resume_music();
+        self.animations.advance(ctx.time.delta().as_secs_f64());
+
+        while ctx.time.check_update_time(TICKS_PER_SECOND) {
+            let transition = self.tick(ctx);
+            if !matches!(transition, Transition::None) {
+                return Ok(transition);
+            }
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut canvas = graphics::Canvas::from_frame(ctx, None);
+        self.renderer
+            .draw(&mut canvas, &self.simulation, &self.animations);
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyInput,
+        _repeat: bool,
+    ) -> GameResult<Transition> {
+        let key = match keycode.keycode {
+            Some(key) => key,
+            None => return Ok(Transition::None),
+        };
+
+        match key {
+            KeyCode::Escape if self.simulation.is_over() => Ok(self.game_over_transition()),
+            KeyCode::Escape => Ok(self.pause()),
+            key => {
+                let binding = Binding::from_key(key, keycode.mods);
+                match self.keybindings.get(&binding).copied() {
+                    None if Binding::is_modifier_key(key) => Ok(Transition::None),
+                    orb => Ok(self.press(ctx, orb)),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Spells that took this many extra presses or more share the last bucket of the summary
+const LAST_BUCKET: usize = 3;
+
+/// Key presses of one Pro mode spell compared to the fewest it could have been cast with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grade {
+    pub required: usize,
+    pub pressed: usize,
+}
+
+impl Grade {
+    // 0 is an optimal cast
+    pub fn extra(&self) -> usize {
+        self.pressed.saturating_sub(self.required)
+    }
+}
+
+/// Every spell of a graded Pro run, in the order they were cast.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Grades {
+    grades: Vec<Grade>,
+}
+
+impl Grades {
+    pub fn push(&mut self, grade: Grade) {
+        self.grades.push(grade);
+    }
+
+    /// Fewest presses over the presses that were made, 1 when every spell was optimal.
+    pub fn efficiency(&self) -> Option<f32> {
+        let pressed: usize = self.grades.iter().map(|grade| grade.pressed).sum();
+        if pressed == 0 {
+            return None;
+        }
+        let required: usize = self.grades.iter().map(|grade| grade.required).sum();
+        Some(required as f32 / pressed as f32)
+    }
+
+    /// One line for the game over screen, e.g. "Efficiency 87%: 20 optimal, 3 at +1".
+    pub fn summary(&self) -> String {
+        let efficiency = match self.efficiency() {
+            Some(efficiency) => efficiency,
+            None => return String::from("No spells cast"),
+        };
+
+        let mut buckets = [0; LAST_BUCKET + 1];
+        for grade in &self.grades {
+            buckets[grade.extra().min(LAST_BUCKET)] += 1;
+        }
+
+        let counts: Vec<String> = buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(extra, count)| match extra {
+                0 => format!("{} optimal", count),
+                LAST_BUCKET => format!("{} at +{} or more", count, extra),
+                _ => format!("{} at +{}", count, extra),
+            })
+            .collect();
+
+        format!(
+            "Efficiency {:.0}%: {}",
+            efficiency * 100.0,
+            counts.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grades(casts: &[(usize, usize)]) -> Grades {
+        let mut grades = Grades::default();
+        for (required, pressed) in casts {
+            grades.push(Grade {
+                required: *required,
+                pressed: *pressed,
+            });
+        }
+        grades
+    }
+
+    #[test]
+    fn test_efficiency() {
+        assert_eq!(Grades::default().efficiency(), None);
+        assert_eq!(grades(&[(4, 4), (2, 2)]).efficiency(), Some(1.0));
+        assert_eq!(grades(&[(4, 5), (1, 3)]).efficiency(), Some(0.625));
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(Grades::default().summary(), "No spells cast");
+        assert_eq!(
+            grades(&[(4, 4), (2, 2), (2, 3), (1, 7)]).summary(),
+            "Efficiency 56%: 2 optimal, 1 at +1, 1 at +3 or more"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::highscores::HighscoreEntry;
+use crate::settings::Settings;
+
+use ggez::graphics::{Canvas, DrawParam};
+use ggez::{
+    glam::*,
+    graphics::{self, Color, Rect},
+    Context, GameResult,
+};
+
+// Left edge of every column as a fraction of the window width
+const COLUMNS: [f32; 5] = [0.22, 0.28, 0.44, 0.56, 0.68];
+
+pub struct HighscoreTable {
+    background: graphics::Mesh,
+    background_draw_param: DrawParam,
+    cells: Vec<(graphics::Text, DrawParam)>,
+}
+
+impl HighscoreTable {
+    pub fn new(
+        ctx: &mut Context,
+        settings: &Settings,
+        entries: &[HighscoreEntry],
+        highlight: Option<usize>,
+        top_y: f32,
+    ) -> GameResult<Self> {
+        let font_size = settings.font_size * 0.7;
+        let row_height = font_size * 1.3;
+
+        let header = ["#", "Date", "Score", "Speed", "Keys"].map(String::from);
+        let mut cells = Self::row(settings, header, top_y, Color::from_rgb(200, 200, 200));
+
+        if entries.is_empty() {
+            let text = graphics::Text::new("No runs yet")
+                .set_scale(font_size)
+                .clone();
+            let dest = Vec2::new(settings.window_width * COLUMNS[0], top_y + row_height);
+            cells.push((text, DrawParam::new().dest(dest).color(Color::WHITE)));
+        }
+
+        for (position, entry) in entries.iter().enumerate() {
+            let color = if Some(position) == highlight {
+                Color::from_rgb(255, 215, 0)
+            } else {
+                Color::WHITE
+            };
+
+            let values = [
+                format!("{}", position + 1),
+                entry.date.clone(),
+                format!("{}", entry.score),
+                entry
+                    .speed
+                    .or_else(|| entry.spells_per_minute())
+                    .map_or_else(String::new, |speed| format!("{:.0}", speed)),
+                entry.keybindings.clone(),
+            ];
+            let y = top_y + row_height * (position + 1) as f32;
+            cells.extend(Self::row(settings, values, y, color));
+        }
+
+        let rows = entries.len().max(1) + 1;
+        let padding = font_size * 0.5;
+        let width = settings.window_width * (1.0 - COLUMNS[0] * 2.0) + padding * 2.0;
+        let height = row_height * rows as f32 + padding * 2.0;
+        let background = graphics::Mesh::new_rounded_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Rect::new(0.0, 0.0, width, height),
+            10.0,
+            Color::from_rgba(0, 0, 0, 180),
+        )?;
+        let background_position = Vec2::new(
+            settings.window_width * COLUMNS[0] - padding,
+            top_y - padding,
+        );
+        let background_draw_param = DrawParam::new().dest(background_position);
+
+        Ok(Self {
+            background,
+            background_draw_param,
+            cells,
+        })
+    }
+
+    fn row(
+        settings: &Settings,
+        values: [String; 5],
+        y: f32,
+        color: Color,
+    ) -> Vec<(graphics::Text, DrawParam)> {
+        values
+            .into_iter()
+            .zip(COLUMNS)
+            .map(|(value, column)| {
+                let text = graphics::Text::new(value)
+                    .set_scale(settings.font_size * 0.7)
+                    .clone();
+                let dest = Vec2::new(settings.window_width * column, y);
+                (text, DrawParam::new().dest(dest).color(color))
+            })
+            .collect()
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas) {
+        canvas.draw(&self.background, self.background_draw_param);
+        for (text, draw_param) in &self.cells {
+            canvas.draw(text, *draw_param);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::{fs, io::Read};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+// How many runs are kept per game mode
+pub const MAX_ENTRIES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GameMode {
+    Normal,
+    Pro,
+    Daily,
+    // Spells have to be cast from the spell slots
+    Slots,
+    // Pro mode where wasted presses cost a budget instead of the run
+    GradedPro,
+    // Pro mode that plays known Invoker combos instead of random spells
+    Combos,
+    // As many spells as possible in a fixed time, wrong casts cost time
+    TimeAttack,
+}
+
+impl GameMode {
+    // One spell at a time that has to be cast with the fewest key presses
+    pub fn is_pro(self) -> bool {
+        matches!(self, GameMode::Pro | GameMode::GradedPro | GameMode::Combos)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HighscoreEntry {
+    pub date: String,
+    pub score: usize,
+    // Time attack runs have no speed, spells don't fall faster over time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    // Seconds a time attack run lasted, every length has a table of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<u64>,
+    pub keybindings: String,
+}
+
+impl HighscoreEntry {
+    pub fn new(score: usize, speed: f32, keybindings: String) -> Self {
+        Self {
+            date: Self::today(),
+            score,
+            speed: Some(speed),
+            time_limit: None,
+            keybindings,
+        }
+    }
+
+    /// A time attack run, `score` is the number of spells cast in `time_limit` seconds.
+    pub fn timed(score: usize, time_limit: u64, keybindings: String) -> Self {
+        Self {
+            date: Self::today(),
+            score,
+            speed: None,
+            time_limit: Some(time_limit),
+            keybindings,
+        }
+    }
+
+    // The speed of a time attack run, which has no speed of its own
+    pub fn spells_per_minute(&self) -> Option<f32> {
+        self.time_limit
+            .map(|time_limit| self.score as f32 * 60.0 / time_limit as f32)
+    }
+
+    fn today() -> String {
+        chrono::Local::now().format("%Y-%m-%d").to_string()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Highscores {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    normal: Vec<HighscoreEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pro: Vec<HighscoreEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    daily: Vec<HighscoreEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    slots: Vec<HighscoreEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    graded_pro: Vec<HighscoreEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    combos: Vec<HighscoreEntry>,
+    // One table per time limit in seconds, only runs of the same length are compared
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    time_attack_by_limit: BTreeMap<String, Vec<HighscoreEntry>>,
+}
+
+impl Highscores {
+    pub fn load() -> Self {
+        let path = Self::get_highscores_path();
+        if !std::path::Path::exists(&path) {
+            return Self::default();
+        }
+
+        let mut contents = String::new();
+        if let Err(e) =
+            fs::File::open(&path).and_then(|mut file| file.read_to_string(&mut contents))
+        {
+            println!("Could not read highscores: {}", e);
+            return Self::default();
+        }
+
+        // A broken highscore file should not prevent anyone from playing
+        match toml::from_str(&contents) {
+            Ok(highscores) => highscores,
+            Err(e) => {
+                println!("Highscores are in the wrong format: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let contents = toml::to_string(self)?;
+        let mut file = fs::File::create(Self::get_highscores_path())?;
+        Ok(file.write_all(contents.as_bytes())?)
+    }
+
+    /// Stores a finished run in the highscore file.
+    /// Returns the updated table, the position of the run in it and whether the run is a new personal best.
+    pub fn record(mode: GameMode, entry: HighscoreEntry) -> (Self, Option<usize>, bool) {
+        let mut highscores = Self::load();
+
+        let personal_best = entry.score > highscores.best(mode, entry.time_limit).unwrap_or(0);
+        let position = highscores.insert(mode, entry);
+
+        if let Err(e) = highscores.save() {
+            println!("Could not save highscores: {}", e);
+        }
+
+        (highscores, position, personal_best)
+    }
+
+    /// The table of `mode`, time attack has one for every `time_limit`.
+    pub fn entries(&self, mode: GameMode, time_limit: Option<u64>) -> &[HighscoreEntry] {
+        match mode {
+            GameMode::Normal => &self.normal,
+            GameMode::Pro => &self.pro,
+            GameMode::Daily => &self.daily,
+            GameMode::Slots => &self.slots,
+            GameMode::GradedPro => &self.graded_pro,
+            GameMode::Combos => &self.combos,
+            GameMode::TimeAttack => time_limit
+                .and_then(|time_limit| self.time_attack_by_limit.get(&time_limit.to_string()))
+                .map_or(&[], Vec::as_slice),
+        }
+    }
+
+    pub fn best(&self, mode: GameMode, time_limit: Option<u64>) -> Option<usize> {
+        self.entries(mode, time_limit)
+            .first()
+            .map(|entry| entry.score)
+    }
+
+    /// Adds a run to the table of the given mode, and of its time limit for time attack.
+    /// Returns the position of the run if it made it into the top `MAX_ENTRIES`.
+    pub fn insert(&mut self, mode: GameMode, entry: HighscoreEntry) -> Option<usize> {
+        let entries = match mode {
+            GameMode::Normal => &mut self.normal,
+            GameMode::Pro => &mut self.pro,
+            GameMode::Daily => &mut self.daily,
+            GameMode::Slots => &mut self.slots,
+            GameMode::GradedPro => &mut self.graded_pro,
+            GameMode::Combos => &mut self.combos,
+            GameMode::TimeAttack => self
+                .time_attack_by_limit
+                .entry(entry.time_limit.unwrap_or_default().to_string())
+                .or_default(),
+        };
+
+        // Every day is a different challenge, so only today's runs are compared
+        if mode == GameMode::Daily {
+            entries.retain(|existing| existing.date == entry.date);
+        }
+
+        // Older runs win ties, so a new run has to beat a score to take its place
+        let position = entries
+            .iter()
+            .position(|existing| existing.score < entry.score)
+            .unwrap_or(entries.len());
+
+        if position >= MAX_ENTRIES {
+            return None;
+        }
+
+        entries.insert(position, entry);
+        entries.truncate(MAX_ENTRIES);
+        Some(position)
+    }
+
+    fn get_highscores_path() -> PathBuf {
+        let project_dirs =
+            ProjectDirs::from("", "", "Invoke").expect("Home directory must be present");
+
+        let config_dir = project_dirs.config_dir();
+        fs::create_dir_all(config_dir).expect("Should be able to create dirs");
+        config_dir.join("highscores.toml")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: usize) -> HighscoreEntry {
+        HighscoreEntry {
+            date: String::from("2024-01-01"),
+            score,
+            speed: Some(1.0),
+            time_limit: None,
+            keybindings: String::from("Q W E R"),
+        }
+    }
+
+    #[test]
+    fn test_insert_keeps_table_sorted_and_bounded() {
+        let mut highscores = Highscores::default();
+
+        assert_eq!(highscores.insert(GameMode::Normal, entry(5)), Some(0));
+        assert_eq!(highscores.insert(GameMode::Normal, entry(10)), Some(0));
+        assert_eq!(highscores.insert(GameMode::Normal, entry(5)), Some(2));
+        assert_eq!(highscores.best(GameMode::Normal, None), Some(10));
+        assert_eq!(highscores.best(GameMode::Pro, None), None);
+
+        for _ in 0..MAX_ENTRIES {
+            highscores.insert(GameMode::Normal, entry(20));
+        }
+        assert_eq!(
+            highscores.entries(GameMode::Normal, None).len(),
+            MAX_ENTRIES
+        );
+        assert_eq!(highscores.insert(GameMode::Normal, entry(20)), None);
+        assert_eq!(highscores.insert(GameMode::Normal, entry(21)), Some(0));
+    }
+
+    #[test]
+    fn test_daily_table_only_keeps_one_day() {
+        let mut highscores = Highscores::default();
+        highscores.insert(GameMode::Daily, entry(50));
+
+        let mut tomorrow = entry(1);
+        tomorrow.date = String::from("2024-01-02");
+        assert_eq!(
+            highscores.insert(GameMode::Daily, tomorrow.clone()),
+            Some(0)
+        );
+        assert_eq!(highscores.entries(GameMode::Daily, None), &[tomorrow]);
+    }
+
+    #[test]
+    fn test_highscores_roundtrip() {
+        let mut highscores = Highscores::default();
+        highscores.insert(GameMode::Pro, entry(3));
+
+        let contents = toml::to_string(&highscores).unwrap();
+        let loaded: Highscores = toml::from_str(&contents).unwrap();
+
+        assert!(loaded.entries(GameMode::Normal, None).is_empty());
+        assert_eq!(loaded.entries(GameMode::Pro, None), &[entry(3)]);
+    }
+
+    #[test]
+    fn test_time_attack_entry_has_no_speed() {
+        let mut highscores = Highscores::default();
+        let entry = HighscoreEntry::timed(21, 30, String::from("Q W E R"));
+        highscores.insert(GameMode::TimeAttack, entry.clone());
+        assert_eq!(entry.spells_per_minute(), Some(42.0));
+
+        let contents = toml::to_string(&highscores).unwrap();
+        assert!(!contents.contains("speed"));
+        assert!(contents.contains("time_limit = 30"));
+
+        let loaded: Highscores = toml::from_str(&contents).unwrap();
+        assert_eq!(loaded.entries(GameMode::TimeAttack, Some(30)), &[entry]);
+    }
+
+    #[test]
+    fn test_time_attack_tables_per_time_limit() {
+        let mut highscores = Highscores::default();
+        let timed = |score, time_limit| HighscoreEntry::timed(score, time_limit, String::new());
+
+        assert_eq!(
+            highscores.insert(GameMode::TimeAttack, timed(40, 60)),
+            Some(0)
+        );
+        // More spells per minute, but a 30s run doesn't compete with the 60s ones
+        assert_eq!(
+            highscores.insert(GameMode::TimeAttack, timed(25, 30)),
+            Some(0)
+        );
+        assert_eq!(
+            highscores.insert(GameMode::TimeAttack, timed(30, 60)),
+            Some(1)
+        );
+
+        assert_eq!(highscores.best(GameMode::TimeAttack, Some(60)), Some(40));
+        assert_eq!(highscores.best(GameMode::TimeAttack, Some(30)), Some(25));
+        assert_eq!(highscores.best(GameMode::TimeAttack, Some(120)), None);
+        assert_eq!(highscores.entries(GameMode::TimeAttack, Some(60)).len(), 2);
+        assert!(highscores.entries(GameMode::TimeAttack, None).is_empty());
+
+        for _ in 0..MAX_ENTRIES {
+            highscores.insert(GameMode::TimeAttack, timed(50, 30));
+        }
+        assert_eq!(highscores.entries(GameMode::TimeAttack, Some(60)).len(), 2);
+        assert_eq!(
+            highscores.entries(GameMode::TimeAttack, Some(30)).len(),
+            MAX_ENTRIES
+        );
+    }
+
+    #[test]
+    fn test_entries_from_before_time_attack() {
+        let contents = r#"
+            [[normal]]
+            date = "2024-01-01"
+            score = 7
+            speed = 120.0
+            keybindings = "Q W E R"
+        "#;
+        let loaded: Highscores = toml::from_str(contents).unwrap();
+        let entry = &loaded.entries(GameMode::Normal, None)[0];
+        assert_eq!(entry.speed, Some(120.0));
+        assert_eq!(entry.time_limit, None);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::layout::Layout;
+use crate::settings::Settings;
+use crate::simulation::Simulation;
+use crate::spells::SpellType;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use ggez::{
+    glam::*,
+    graphics::{self, Canvas, Color, DrawParam, Rect, TextAlign, TextFragment, TextLayout},
+};
+use serde::{Deserialize, Serialize};
+
+/// Which parts of the HUD are shown, stored as the `[hud]` table of settings.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HudSettings {
+    pub score: bool,
+    pub time: bool,
+    pub speed: bool,
+    pub streak: bool,
+    // Icon of the spell the orbs in the buffer would invoke
+    pub preview: bool,
+    // Pro mode: the fewest presses for the spell, until the first key is pressed
+    pub hint: bool,
+}
+
+impl Default for HudSettings {
+    fn default() -> Self {
+        Self {
+            score: true,
+            time: true,
+            speed: true,
+            streak: true,
+            preview: true,
+            hint: false,
+        }
+    }
+}
+
+/// Live numbers of the run in the top right corner, the combo of a combo drill in the top
+/// left one, the spell preview right of the orbs, the spell slots left of them and the
+/// Pro mode hint above them.
+pub struct Hud {
+    settings: HudSettings,
+    // Settings in use, the slots and the hint show the keys they are bound to
+    // and the combo the names of its spells
+    bindings: Settings,
+}
+
+impl Hud {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            settings: settings.hud,
+            bindings: settings.clone(),
+        }
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas,
+        layout: &Layout,
+        simulation: &Simulation,
+        spell_icons: &HashMap<SpellType, graphics::Image>,
+    ) {
+        let lines = self.lines(simulation);
+        if !lines.is_empty() {
+            let text = graphics::Text::new(lines.join("\n"))
+                .set_scale(layout.font_size * 0.8)
+                .set_layout(TextLayout {
+                    h_align: TextAlign::End,
+                    v_align: TextAlign::Begin,
+                })
+                .clone();
+            let dest = Vec2::new(layout.width - layout.margin, layout.margin);
+            canvas.draw(&text, DrawParam::new().dest(dest).color(Color::WHITE));
+        }
+
+        self.draw_slots(canvas, layout, simulation, spell_icons);
+        self.draw_combo(canvas, layout, simulation);
+
+        // Only before the first press, afterwards the player has to find the way on their own
+        if self.settings.hint && simulation.key_presses() == 0 {
+            if let Some(hint) = simulation.hint() {
+                let text = graphics::Text::new(self.bindings.hint_text(&hint))
+                    .set_scale(layout.font_size * 0.7)
+                    .set_layout(TextLayout {
+                        h_align: TextAlign::Middle,
+                        v_align: TextAlign::End,
+                    })
+                    .clone();
+                let dest = Vec2::new(
+                    layout.orb_x[1] + layout.orb_size / 2.0,
+                    layout.buffer_y - layout.margin / 2.0,
+                );
+                canvas.draw(
+                    &text,
+                    DrawParam::new()
+                        .dest(dest)
+                        .color(Color::from_rgb(255, 215, 120)),
+                );
+            }
+        }
+
+        if !self.settings.preview {
+            return;
+        }
+        if let Some(spell) = simulation.preview() {
+            let icon = &spell_icons[&spell.id];
+            let scale = layout.orb_size / icon.width() as f32;
+            let dest = Vec2::new(layout.orb_x[2] + layout.orb_size * 1.5, layout.buffer_y);
+            let draw_param = DrawParam::new()
+                .dest(dest)
+                .scale(Vec2::new(scale, scale))
+                .color(Color::new(1.0, 1.0, 1.0, 0.7));
+            canvas.draw(icon, draw_param);
+        }
+    }
+
+    // The slots are always shown, casting from them is part of the game
+    fn draw_slots(
+        &self,
+        canvas: &mut Canvas,
+        layout: &Layout,
+        simulation: &Simulation,
+        spell_icons: &HashMap<SpellType, graphics::Image>,
+    ) {
+        let slot_distance = layout.orb_x[1] - layout.orb_x[0];
+        for (index, spell) in simulation.slots().iter().enumerate() {
+            // Left to right like D and F on a keyboard
+            let x = layout.orb_x[0] - slot_distance * (2.5 - index as f32);
+            match spell {
+                Some(spell) => {
+                    let icon = &spell_icons[spell];
+                    let scale = layout.orb_size / icon.width() as f32;
+                    let draw_param = DrawParam::new()
+                        .dest(Vec2::new(x, layout.buffer_y))
+                        .scale(Vec2::new(scale, scale));
+                    canvas.draw(icon, draw_param);
+                }
+                None => {
+                    let slot = Rect::new(x, layout.buffer_y, layout.orb_size, layout.orb_size);
+                    canvas.draw(
+                        &graphics::Quad,
+                        DrawParam::new()
+                            .dest_rect(slot)
+                            .color(Color::new(1.0, 1.0, 1.0, 0.15)),
+                    );
+                }
+            }
+
+            let key = [self.bindings.first_slot_key, self.bindings.second_slot_key][index];
+            let key = graphics::Text::new(key.to_string())
+                .set_scale(layout.font_size * 0.6)
+                .set_layout(TextLayout {
+                    h_align: TextAlign::Middle,
+                    v_align: TextAlign::End,
+                })
+                .clone();
+            let dest = Vec2::new(
+                x + layout.orb_size / 2.0,
+                layout.buffer_y - layout.margin / 2.0,
+            );
+            canvas.draw(&key, DrawParam::new().dest(dest).color(Color::WHITE));
+        }
+    }
+
+    // Part of the rules of a combo drill, so it can't be turned off. Spells that
+    // were cast are greyed out and the current one is highlighted
+    fn draw_combo(&self, canvas: &mut Canvas, layout: &Layout, simulation: &Simulation) {
+        let combo = match simulation.combo() {
+            Some(combo) => combo,
+            None => return,
+        };
+
+        let mut text = graphics::Text::new(format!("{}\n", combo.combo.name));
+        for (index, spell) in combo.combo.spells.iter().enumerate() {
+            let name = self
+                .bindings
+                .spell_book
+                .spells()
+                .iter()
+                .find(|definition| &definition.id == spell)
+                .map_or_else(|| spell.to_string(), |definition| definition.name.clone());
+            let color = match index.cmp(&combo.cast) {
+                Ordering::Less => Color::new(1.0, 1.0, 1.0, 0.4),
+                Ordering::Equal => Color::from_rgb(255, 215, 120),
+                Ordering::Greater => Color::WHITE,
+            };
+            text.add(TextFragment::new(format!("{}\n", name)).color(color));
+        }
+
+        text.add(format!("Fewest keys {}\n", combo.fewest_key_presses));
+        let time = simulation.elapsed() - combo.started;
+        text.add(format!("Combo time {:.1}s", time.as_secs_f32()));
+        if let Some(last) = simulation.combo_results().last() {
+            text.add(format!("\nLast combo {:.2}s", last.time.as_secs_f32()));
+        }
+
+        text.set_scale(layout.font_size * 0.8)
+            .set_layout(TextLayout {
+                h_align: TextAlign::Begin,
+                v_align: TextAlign::Begin,
+            });
+        let dest = Vec2::new(layout.margin, layout.margin);
+        canvas.draw(&text, DrawParam::new().dest(dest));
+    }
+
+    fn lines(&self, simulation: &Simulation) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.settings.score {
+            lines.push(format!("Score {}", simulation.score()));
+        }
+        if self.settings.time {
+            lines.push(format!("Time {:.1}s", simulation.elapsed().as_secs_f32()));
+        }
+        // Time attack spells don't fall, they have no speed
+        if self.settings.speed && simulation.time_left().is_none() {
+            lines.push(format!("Speed {:.0}", simulation.speed()));
+        }
+        if self.settings.streak {
+            lines.push(format!("Streak {}", simulation.streak()));
+        }
+        // Part of the rules of a graded run, so it can't be turned off
+        if let Some(budget) = simulation.budget() {
+            lines.push(format!("Budget {}", budget));
+        }
+        if let Some(time_left) = simulation.time_left() {
+            lines.push(format!("Time left {:.1}s", time_left.as_secs_f32()));
+            lines.push(format!("Errors {}", simulation.errors()));
+        }
+        lines
+    }
+}
@@ -0,0 +1,90 @@
+// Size of the window the artwork was made for, everything is scaled from it
+const REFERENCE_SIZE: f32 = 1024.0;
+
+// Sizes of the built-in icons in the reference window
+const SPELL_ICON_SIZE: f32 = 72.0;
+const ORB_ICON_SIZE: f32 = 77.0;
+const ORB_PADDING: f32 = 10.0;
+
+/// Sizes and positions of everything drawn during a run, computed from the window size.
+/// Plain numbers only, so the simulation can use it as well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub width: f32,
+    pub height: f32,
+    // How much the artwork is scaled compared to the reference window
+    pub scale: f32,
+    pub spell_size: f32,
+    pub orb_size: f32,
+    // Left edge of every orb of the buffer and the top of the buffer
+    pub orb_x: [f32; 3],
+    pub buffer_y: f32,
+    pub font_size: f32,
+    // Distance of the HUD from the window edges
+    pub margin: f32,
+}
+
+impl Layout {
+    pub fn new(width: f32, height: f32) -> Self {
+        // Icons stay square, so the shorter side decides how large they are
+        let scale = width.min(height) / REFERENCE_SIZE;
+
+        let orb_size = ORB_ICON_SIZE * scale;
+        let padding = ORB_PADDING * scale;
+        let buffer_width = orb_size * 3.0 + padding * 2.0;
+        let first_x = width / 2.0 - buffer_width / 2.0;
+        let orb_x = [0.0, 1.0, 2.0].map(|i| first_x + (orb_size + padding) * i);
+
+        Self {
+            width,
+            height,
+            scale,
+            spell_size: SPELL_ICON_SIZE * scale,
+            orb_size,
+            orb_x,
+            buffer_y: height - orb_size * 1.5,
+            font_size: height * 0.03,
+            margin: 16.0 * scale,
+        }
+    }
+
+    /// Left edge of a new spell, `position` between 0 and 1 picks where in the lane it spawns.
+    /// The lane keeps one icon of space on both sides.
+    pub fn spawn_x(&self, position: f32) -> f32 {
+        self.spell_size + position * (self.width - self.spell_size * 2.0)
+    }
+
+    /// Scale that stretches an image over the whole window.
+    pub fn fill_scale(&self, image_width: f32, image_height: f32) -> (f32, f32) {
+        (self.width / image_width, self.height / image_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_window() {
+        let layout = Layout::new(1024.0, 1024.0);
+        assert_eq!(layout.spell_size, 72.0);
+        assert_eq!(layout.orb_size, 77.0);
+        assert_eq!(layout.orb_x, [386.5, 473.5, 560.5]);
+        assert_eq!(layout.buffer_y, 1024.0 - 77.0 * 1.5);
+        assert_eq!(layout.spawn_x(0.0), 72.0);
+        assert_eq!(layout.spawn_x(1.0), 1024.0 - 72.0);
+    }
+
+    #[test]
+    fn test_scales_with_shorter_side() {
+        let layout = Layout::new(1920.0, 512.0);
+        assert_eq!(layout.scale, 0.5);
+        assert_eq!(layout.spell_size, 36.0);
+
+        // The buffer stays centered and inside the window
+        let right = layout.orb_x[2] + layout.orb_size;
+        assert_eq!(layout.orb_x[0], 1920.0 - right);
+        assert!(layout.buffer_y + layout.orb_size < 512.0);
+        assert_eq!(layout.fill_scale(1024.0, 1024.0), (1.875, 0.5));
+    }
+}
@@ -1,22 +1,56 @@
+mod animations;
 mod assets;
+mod audio;
+mod bindings;
 mod buttons;
+mod cli;
+mod combo_book;
+mod game_renderer;
 mod game_states;
+mod grades;
+mod highscore_table;
+mod highscores;
+mod hud;
 mod input_buffer;
+mod layout;
 mod orbs;
+mod replay;
+mod run_history;
 mod settings;
+mod simulation;
+mod spawner;
+mod spell_book;
 mod spells;
 mod state_machine;
+mod statistics;
 
-use crate::game_states::menu_state::MenuState;
-
-use settings::Settings;
-use state_machine::StateMachine;
+use cli::Options;
+use highscores::Highscores;
+use settings::{Settings, WINDOW_HEIGHTS, WINDOW_WIDTHS};
+use state_machine::{Screen, StateMachine};
 
 use ggez::{event, GameResult};
 
 use std::{env, path};
 
 fn main() -> GameResult {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if options.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if options.highscores {
+        print!("{}", cli::highscore_listing(&Highscores::load()));
+        return Ok(());
+    }
+
     let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
         path.push("resources");
@@ -25,10 +59,18 @@ fn main() -> GameResult {
         path::PathBuf::from("./resources")
     };
 
-    let settings = Settings::new().unwrap();
+    let (settings, problems) = Settings::load(options.settings_path.as_deref());
+    let settings = match options.apply(&settings) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
 
     let window_mode = ggez::conf::WindowMode::default()
         .resizable(true)
+        .min_dimensions(*WINDOW_WIDTHS.start(), *WINDOW_HEIGHTS.start())
         .dimensions(settings.window_width, settings.window_height);
 
     let cb = ggez::ContextBuilder::new("Invoke", "Popov Philipp")
@@ -36,9 +78,18 @@ fn main() -> GameResult {
         .add_resource_path(resource_dir);
     let (mut ctx, event_loop) = cb.build()?;
 
-    let initial_state = MenuState::new(&mut ctx, &settings)?;
+    // A run started from the command line skips the menu, settings that had to be
+    // replaced are then only printed
+    let initial_screen = match options.mode {
+        Some(mode) => Screen::run(mode, options.seed),
+        None if problems.is_empty() => Screen::Menu,
+        None => Screen::Notice {
+            title: String::from("Some settings were reset"),
+            notices: problems,
+        },
+    };
 
-    let state_machine = StateMachine::new(Box::new(initial_state), settings);
+    let state_machine = StateMachine::new(&mut ctx, initial_screen, settings)?;
 
     event::run(ctx, event_loop, state_machine)
 }
@@ -1,7 +1,6 @@
-use crate::assets::{EXORT, INVOKE, QUAS, WEX};
-use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub enum OrbType {
     Quas,
     Wex,
@@ -9,26 +8,8 @@ pub enum OrbType {
 
     // Not an orb techincally but who cares?
     Invoke,
-}
-
-#[derive(Clone)]
-pub struct Orb {
-    pub orb_type: OrbType,
-    pub orb_image: graphics::Image,
-}
-
-impl Orb {
-    pub fn new(ctx: &mut Context, orb_type: OrbType) -> GameResult<Self> {
-        let orb_image = match orb_type {
-            OrbType::Quas => graphics::Image::from_bytes(ctx, QUAS)?,
-            OrbType::Wex => graphics::Image::from_bytes(ctx, WEX)?,
-            OrbType::Exort => graphics::Image::from_bytes(ctx, EXORT)?,
-            OrbType::Invoke => graphics::Image::from_bytes(ctx, INVOKE)?,
-        };
 
-        Ok(Self {
-            orb_type,
-            orb_image,
-        })
-    }
+    // Cast the spell in the first (D) or second (F) spell slot
+    FirstSlot,
+    SecondSlot,
 }
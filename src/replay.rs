@@ -0,0 +1,145 @@
+use crate::combo_book::ComboBook;
+use crate::highscores::GameMode;
+use crate::orbs::OrbType;
+use crate::settings::Settings;
+use crate::spawner::Spawner;
+use crate::spell_book::SpellBook;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{fs, io::Read};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+// A key press and the game tick it happened after
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ReplayInput {
+    pub tick: u64,
+    // Keys that are not bound to an orb still count in Pro mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orb: Option<OrbType>,
+}
+
+/// Everything needed to play a run again: with the same seed, spawner, spells and window size
+/// the spells fall exactly the same way, so only the inputs have to be stored.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Replay {
+    pub version: u32,
+    pub mode: GameMode,
+    pub seed: u64,
+    pub window_width: f32,
+    pub window_height: f32,
+    #[serde(default)]
+    pub spawner: Spawner,
+    // Replays recorded before spells could be customized used the built-in ones
+    #[serde(default)]
+    pub spell_book: SpellBook,
+    // Only combo drills need it, replays from before them play without
+    #[serde(default)]
+    pub combo_book: ComboBook,
+    // Seconds of a time attack run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<u32>,
+    #[serde(default)]
+    pub inputs: Vec<ReplayInput>,
+}
+
+impl Replay {
+    pub fn new(mode: GameMode, seed: u64, settings: &Settings, spawner: Spawner) -> Self {
+        Self {
+            version: REPLAY_FORMAT_VERSION,
+            mode,
+            seed,
+            window_width: settings.window_width,
+            window_height: settings.window_height,
+            spawner,
+            spell_book: settings.spell_book.clone(),
+            combo_book: settings.combo_book.clone(),
+            time_limit: (mode == GameMode::TimeAttack).then_some(settings.time_limit),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, orb: Option<OrbType>) {
+        self.inputs.push(ReplayInput { tick, orb });
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let value: toml::Value = toml::from_str(contents)?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_integer())
+            .ok_or("Replay has no version")?;
+
+        match version {
+            1 => Ok(value.try_into()?),
+            _ => Err(format!("Unsupported replay version {}", version).into()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut contents = String::new();
+        fs::File::open(path)?.read_to_string(&mut contents)?;
+        Self::parse(&contents)
+    }
+
+    // Replays are named like the run they belong to
+    pub fn save(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let file_name = format!("{}.toml", name.replace([':', '.'], "-"));
+        let contents = toml::to_string(self)?;
+        let mut file = fs::File::create(Self::get_replays_dir().join(file_name))?;
+        Ok(file.write_all(contents.as_bytes())?)
+    }
+
+    pub fn latest_path() -> Option<PathBuf> {
+        let entries = fs::read_dir(Self::get_replays_dir()).ok()?;
+
+        // File names start with the date, so the last one is the newest
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "toml")
+            })
+            .max()
+    }
+
+    fn get_replays_dir() -> PathBuf {
+        let project_dirs =
+            ProjectDirs::from("", "", "Invoke").expect("Home directory must be present");
+
+        let replays_dir = project_dirs.data_dir().join("replays");
+        fs::create_dir_all(&replays_dir).expect("Should be able to create dirs");
+        replays_dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_roundtrip() {
+        let mut replay = Replay {
+            version: REPLAY_FORMAT_VERSION,
+            mode: GameMode::Pro,
+            seed: 1234,
+            window_width: 1024.0,
+            window_height: 768.0,
+            spawner: Spawner::uniform(),
+            spell_book: SpellBook::default(),
+            combo_book: ComboBook::default(),
+            time_limit: None,
+            inputs: Vec::new(),
+        };
+        replay.record(3, Some(OrbType::Quas));
+        replay.record(3, None);
+        replay.record(40, Some(OrbType::Invoke));
+
+        let contents = toml::to_string(&replay).unwrap();
+        assert_eq!(Replay::parse(&contents).unwrap(), replay);
+    }
+}
@@ -0,0 +1,326 @@
+use crate::highscores::GameMode;
+use crate::simulation::Event;
+use crate::spells::SpellType;
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{fs, io::Read};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+// Bump this whenever `RunRecord` changes in a way old files can't be read with,
+// and teach `RunRecord::parse` how to read the previous version
+pub const RUN_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "reason")]
+pub enum RunEnd {
+    // `target` is the spell the player most likely went for:
+    // the current spell in Pro mode or the lowest spell on the screen otherwise
+    WrongSpell {
+        cast: Vec<char>,
+        target: Option<SpellType>,
+    },
+    SpellReachedBottom {
+        spell: SpellType,
+    },
+    WrongKeyPressCount {
+        spell: SpellType,
+        pressed: usize,
+        required: usize,
+    },
+    // The time of a time attack run ran out
+    TimeUp,
+    Quit,
+}
+
+impl RunEnd {
+    // Spell that ended the run, if the run was not quit
+    pub fn killer(&self) -> Option<SpellType> {
+        match self {
+            RunEnd::WrongSpell { target, .. } => target.clone(),
+            RunEnd::SpellReachedBottom { spell } => Some(spell.clone()),
+            RunEnd::WrongKeyPressCount { spell, .. } => Some(spell.clone()),
+            RunEnd::TimeUp | RunEnd::Quit => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SpellRecord {
+    pub spell: SpellType,
+    // Seconds since the start of the run
+    pub spawned_at: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cast_at: Option<f32>,
+    // Orbs pressed since the previous invoke, in order. A spell that wasn't cast
+    // has the orbs pressed for it before the run ended
+    #[serde(default)]
+    pub keys: Vec<char>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RunRecord {
+    pub version: u32,
+    pub mode: GameMode,
+    // Missing in runs recorded before spawning was seeded
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub started_at: String,
+    pub score: usize,
+    pub duration: f32,
+    pub end: RunEnd,
+    #[serde(default)]
+    pub spells: Vec<SpellRecord>,
+}
+
+impl RunRecord {
+    pub fn parse(contents: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let value: toml::Value = toml::from_str(contents)?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_integer())
+            .ok_or("Run has no version")?;
+
+        match version {
+            1 => Ok(value.try_into()?),
+            _ => Err(format!("Unsupported run version {}", version).into()),
+        }
+    }
+
+    /// Reads every recorded run, oldest first. Runs that can't be read are skipped.
+    pub fn load_all() -> Vec<Self> {
+        let entries = match fs::read_dir(Self::get_runs_dir()) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "toml")
+            })
+            .collect();
+        // File names start with the date, so this is chronological
+        paths.sort();
+
+        let mut runs = Vec::with_capacity(paths.len());
+        for path in paths {
+            let mut contents = String::new();
+            let read =
+                fs::File::open(&path).and_then(|mut file| file.read_to_string(&mut contents));
+
+            match read
+                .map_err(|e| e.into())
+                .and_then(|_| Self::parse(&contents))
+            {
+                Ok(run) => runs.push(run),
+                Err(e) => println!("Skipping run {}: {}", path.display(), e),
+            }
+        }
+        runs
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let file_name = format!("{}.toml", self.started_at.replace([':', '.'], "-"));
+        let contents = toml::to_string(self)?;
+        let mut file = fs::File::create(Self::get_runs_dir().join(file_name))?;
+        Ok(file.write_all(contents.as_bytes())?)
+    }
+
+    fn get_runs_dir() -> PathBuf {
+        let project_dirs =
+            ProjectDirs::from("", "", "Invoke").expect("Home directory must be present");
+
+        let runs_dir = project_dirs.data_dir().join("runs");
+        fs::create_dir_all(&runs_dir).expect("Should be able to create dirs");
+        runs_dir
+    }
+}
+
+/// Collects everything that happens during a run and writes it to the run history once the run is over.
+pub struct RunRecorder {
+    mode: GameMode,
+    seed: u64,
+    started_at: String,
+    spells: Vec<SpellRecord>,
+    keys: Vec<char>,
+    finished: bool,
+}
+
+impl RunRecorder {
+    pub fn new(mode: GameMode, seed: u64) -> Self {
+        let started_at = chrono::Local::now()
+            .format("%Y-%m-%dT%H:%M:%S%.3f")
+            .to_string();
+
+        Self {
+            mode,
+            seed,
+            started_at,
+            spells: Vec::new(),
+            keys: Vec::new(),
+            finished: false,
+        }
+    }
+
+    pub fn started_at(&self) -> &str {
+        &self.started_at
+    }
+
+    // Keeps the run history in sync with what happens in the simulation
+    pub fn record(&mut self, event: &Event) {
+        match event {
+            Event::SpellSpawned { spell, at, .. } => {
+                self.spawned(spell.clone(), *at);
+            }
+            Event::OrbPressed(orb) => self.orb_pressed(*orb),
+            Event::SpellCast { id, at, .. } => self.cast(*id, *at),
+            Event::GameOver(_) => {}
+        }
+    }
+
+    // Returns the id the spell is referred to by later on
+    pub fn spawned(&mut self, spell: SpellType, at: Duration) -> usize {
+        self.spells.push(SpellRecord {
+            spell,
+            spawned_at: at.as_secs_f32(),
+            cast_at: None,
+            keys: Vec::new(),
+        });
+        self.spells.len() - 1
+    }
+
+    pub fn orb_pressed(&mut self, orb: char) {
+        self.keys.push(orb);
+    }
+
+    pub fn cast(&mut self, id: usize, at: Duration) {
+        if let Some(record) = self.spells.get_mut(id) {
+            record.cast_at = Some(at.as_secs_f32());
+            record.keys = std::mem::take(&mut self.keys);
+        }
+    }
+
+    /// Writes the run to disk. Only the first call does anything.
+    pub fn finish(&mut self, score: usize, duration: Duration, end: RunEnd) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        let record = self.to_record(score, duration, end);
+        if let Err(e) = record.save() {
+            println!("Could not save run history: {}", e);
+        }
+    }
+
+    fn to_record(&self, score: usize, duration: Duration, end: RunEnd) -> RunRecord {
+        let mut spells = self.spells.clone();
+
+        // Orbs pressed since the last cast belong to the spell the run ended on,
+        // or to the newest spell that wasn't cast if no spell ended it
+        let killer = end.killer();
+        let pending = spells
+            .iter()
+            .rposition(|record| {
+                record.cast_at.is_none()
+                    && killer.as_ref().is_some_and(|spell| &record.spell == spell)
+            })
+            .or_else(|| spells.iter().rposition(|record| record.cast_at.is_none()));
+        if let Some(index) = pending {
+            spells[index].keys = self.keys.clone();
+        }
+
+        RunRecord {
+            version: RUN_FORMAT_VERSION,
+            mode: self.mode,
+            seed: Some(self.seed),
+            started_at: self.started_at.clone(),
+            score,
+            duration: duration.as_secs_f32(),
+            end,
+            spells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_record_roundtrip() {
+        let mut recorder = RunRecorder::new(GameMode::Pro, 42);
+        let first = recorder.spawned(SpellType::new("Tornado"), Duration::from_secs(0));
+        recorder.spawned(SpellType::new("Emp"), Duration::from_millis(1500));
+        recorder.orb_pressed('Q');
+        recorder.orb_pressed('W');
+        recorder.orb_pressed('W');
+        recorder.cast(first, Duration::from_secs(1));
+
+        let end = RunEnd::WrongKeyPressCount {
+            spell: SpellType::new("Emp"),
+            pressed: 5,
+            required: 4,
+        };
+        let record = recorder.to_record(1, Duration::from_secs(3), end);
+
+        let contents = toml::to_string(&record).unwrap();
+        let parsed = RunRecord::parse(&contents).unwrap();
+
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.spells[0].keys, vec!['Q', 'W', 'W']);
+        assert_eq!(parsed.spells[1].cast_at, None);
+    }
+
+    #[test]
+    fn test_keys_of_the_last_cast_are_recorded() {
+        let mut recorder = RunRecorder::new(GameMode::Normal, 42);
+        recorder.spawned(SpellType::new("Tornado"), Duration::from_secs(0));
+        recorder.spawned(SpellType::new("Emp"), Duration::from_secs(1));
+        recorder.spawned(SpellType::new("Alacrity"), Duration::from_secs(2));
+        recorder.orb_pressed('W');
+        recorder.orb_pressed('W');
+        recorder.orb_pressed('Q');
+
+        let end = RunEnd::WrongSpell {
+            cast: vec!['Q', 'W', 'W'],
+            target: Some(SpellType::new("Emp")),
+        };
+        let record = recorder.to_record(0, Duration::from_secs(3), end);
+        assert_eq!(record.spells[1].keys, vec!['W', 'W', 'Q']);
+        assert!(record.spells[2].keys.is_empty());
+
+        // A quit run keeps them with the newest spell
+        let record = recorder.to_record(0, Duration::from_secs(3), RunEnd::Quit);
+        assert_eq!(record.spells[2].keys, vec!['W', 'W', 'Q']);
+    }
+
+    #[test]
+    fn test_parse_run_without_seed() {
+        let contents = r#"
+            version = 1
+            mode = "Normal"
+            started_at = "2024-01-01T00:00:00.000"
+            score = 0
+            duration = 1.0
+
+            [end]
+            reason = "Quit"
+        "#;
+
+        let parsed = RunRecord::parse(contents).unwrap();
+        assert_eq!(parsed.seed, None);
+        assert!(parsed.spells.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_versions() {
+        assert!(RunRecord::parse("mode = \"Normal\"").is_err());
+        assert!(RunRecord::parse("version = 999").is_err());
+    }
+}
@@ -1,12 +1,36 @@
+use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
-use std::{fs, io::Read};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 
 use directories::ProjectDirs;
 use ggez::{glam::Vec2, graphics};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use ggez::input::keyboard::KeyCode;
+use crate::assets::{png_size, BACKGROUND_IMAGE};
+use crate::audio::{AudioSettings, MAX_VOLUME};
+use crate::bindings::Binding;
+use crate::combo_book::ComboBook;
+use crate::hud::HudSettings;
+use crate::layout::Layout;
+use crate::orbs::OrbType;
+use crate::simulation::Hint;
+use crate::spell_book::SpellBook;
+
+use std::collections::HashMap;
+
+// Anything outside of these can't be shown on a screen or is too small to play on
+pub const WINDOW_WIDTHS: RangeInclusive<f32> = 320.0..=7680.0;
+pub const WINDOW_HEIGHTS: RangeInclusive<f32> = 240.0..=4320.0;
+// Seconds a time attack run can last
+pub const TIME_LIMITS: [u32; 3] = [30, 60, 120];
+
+// Written to settings.toml, bumped together with a new entry in MIGRATIONS
+const SETTINGS_VERSION: u32 = 2;
+
+// MIGRATIONS[n] upgrades a settings.toml of version n to version n + 1
+const MIGRATIONS: [fn(&mut toml::value::Table); SETTINGS_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2];
 
 #[derive(Debug, Clone)]
 pub struct Settings {
@@ -14,139 +38,992 @@ pub struct Settings {
     pub window_height: f32,
     pub background_draw_param: graphics::DrawParam,
     pub font_size: f32,
-    pub quas_key: KeyCode,
-    pub wex_key: KeyCode,
-    pub exort_key: KeyCode,
-    pub invoke_key: KeyCode,
+    pub quas_key: Binding,
+    pub wex_key: Binding,
+    pub exort_key: Binding,
+    pub invoke_key: Binding,
+    pub first_slot_key: Binding,
+    pub second_slot_key: Binding,
+    pub adaptive_spawning: bool,
+    pub time_limit: u32,
+    pub hud: HudSettings,
+    pub audio: AudioSettings,
+    pub spell_book: SpellBook,
+    pub combo_book: ComboBook,
+    // Where the settings are saved to, settings.toml unless another file was given
+    path: PathBuf,
+    // Set when the command line overrode some of the settings for this session
+    overrides: Option<Box<Overrides>>,
 }
 
-impl Settings {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let user_settings = UserSettings::new()?;
-        Ok(Settings::from(user_settings))
-    }
-
-    fn string_to_keycode(key_mapping: String) -> KeyCode {
-        match key_mapping.as_str() {
-            "A" => KeyCode::A,
-            "B" => KeyCode::B,
-            "C" => KeyCode::C,
-            "D" => KeyCode::D,
-            "E" => KeyCode::E,
-            "F" => KeyCode::F,
-            "G" => KeyCode::G,
-            "H" => KeyCode::H,
-            "I" => KeyCode::I,
-            "J" => KeyCode::J,
-            "K" => KeyCode::K,
-            "L" => KeyCode::L,
-            "M" => KeyCode::M,
-            "N" => KeyCode::N,
-            "O" => KeyCode::O,
-            "P" => KeyCode::P,
-            "Q" => KeyCode::Q,
-            "R" => KeyCode::R,
-            "S" => KeyCode::S,
-            "T" => KeyCode::T,
-            "U" => KeyCode::U,
-            "V" => KeyCode::V,
-            "W" => KeyCode::W,
-            "X" => KeyCode::X,
-            "Y" => KeyCode::Y,
-            "Z" => KeyCode::Z,
-            _ => unreachable!(),
+// What the settings file and the command line had when the session started
+#[derive(Debug, Clone)]
+struct Overrides {
+    file: UserSettings,
+    session: UserSettings,
+}
+
+impl Overrides {
+    // Values that are still the ones from the command line go back to the ones of the file
+    fn restore(&self, user_settings: &mut UserSettings) {
+        let (file, session) = (&self.file, &self.session);
+        if (user_settings.window_width, user_settings.window_height)
+            == (session.window_width, session.window_height)
+        {
+            user_settings.window_width = file.window_width;
+            user_settings.window_height = file.window_height;
         }
+
+        let restore = |value: &mut String, session: &String, file: &String| {
+            if value == session {
+                *value = file.clone();
+            }
+        };
+        restore(
+            &mut user_settings.quas_key,
+            &session.quas_key,
+            &file.quas_key,
+        );
+        restore(&mut user_settings.wex_key, &session.wex_key, &file.wex_key);
+        restore(
+            &mut user_settings.exort_key,
+            &session.exort_key,
+            &file.exort_key,
+        );
+        restore(
+            &mut user_settings.invoke_key,
+            &session.invoke_key,
+            &file.invoke_key,
+        );
+        restore(
+            &mut user_settings.first_slot_key,
+            &session.first_slot_key,
+            &file.first_slot_key,
+        );
+        restore(
+            &mut user_settings.second_slot_key,
+            &session.second_slot_key,
+            &file.second_slot_key,
+        );
     }
 }
 
-impl From<UserSettings> for Settings {
-    fn from(user_settings: UserSettings) -> Self {
-        let font_size = user_settings.window_height * 0.03;
+impl Settings {
+    /// Loads settings.toml, or the given file instead. Fields that can't be used fall
+    /// back to their defaults and what was wrong with them is returned, so the player can be told.
+    pub fn load(path: Option<&Path>) -> (Self, Vec<String>) {
+        let path = path.map_or_else(UserSettings::get_settings_path, Path::to_path_buf);
+        let (user_settings, mut problems) = UserSettings::new(&path);
+        let (mut settings, invalid) = Self::validate(user_settings);
+        problems.extend(invalid);
+
+        // Custom spells and combos live next to the settings file, wherever that is
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let (spell_book, invalid) = SpellBook::load(dir);
+        problems.extend(invalid);
+        let (combo_book, invalid) = ComboBook::load(dir, &spell_book);
+        problems.extend(invalid);
+        settings.spell_book = spell_book;
+        settings.combo_book = combo_book;
+        settings.path = path;
+
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        (settings, problems)
+    }
+
+    /// Writes the settings back to the file they were loaded from. Overrides from the
+    /// command line are left out unless they were changed since.
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.to_user_settings().save(&self.path)
+    }
+
+    /// Remembers where these settings differ from `file`, the settings in the file,
+    /// so saving keeps the file's values for them.
+    pub fn overriding(mut self, file: &Settings) -> Self {
+        self.overrides = Some(Box::new(Overrides {
+            file: UserSettings::from(file),
+            session: UserSettings::from(&self),
+        }));
+        self
+    }
+
+    fn to_user_settings(&self) -> UserSettings {
+        let mut user_settings = UserSettings::from(self);
+        if let Some(overrides) = &self.overrides {
+            overrides.restore(&mut user_settings);
+        }
+        user_settings
+    }
+
+    pub fn bindings(&self) -> [(&'static str, Binding); 6] {
+        [
+            ("Quas", self.quas_key),
+            ("Wex", self.wex_key),
+            ("Exort", self.exort_key),
+            ("Invoke", self.invoke_key),
+            ("First slot", self.first_slot_key),
+            ("Second slot", self.second_slot_key),
+        ]
+    }
 
-        let scale_w = user_settings.window_width / 1024.0;
-        let scale_y = user_settings.window_height / 1024.0;
-        let scale = Vec2::new(scale_w, scale_y);
-        let background_draw_param = graphics::DrawParam::new().scale(scale);
+    // Every orb and spell slot needs a key of its own
+    pub fn binding_conflict(&self) -> Option<String> {
+        let bindings = self.bindings();
+        for (i, (orb, key)) in bindings.iter().enumerate() {
+            let conflict = bindings[i + 1..]
+                .iter()
+                .find(|(_, other_key)| other_key == key);
+            if let Some((other, _)) = conflict {
+                return Some(format!("{} and {} are both bound to {}", orb, other, key));
+            }
+        }
+        None
+    }
 
-        let quas_key = Self::string_to_keycode(user_settings.quas_key);
-        let wex_key = Self::string_to_keycode(user_settings.wex_key);
-        let exort_key = Self::string_to_keycode(user_settings.exort_key);
-        let invoke_key = Self::string_to_keycode(user_settings.invoke_key);
+    // The orb every binding presses, used by the game states to look up input
+    pub fn keybindings(&self) -> HashMap<Binding, OrbType> {
+        let mut keybindings = HashMap::with_capacity(6);
+        keybindings.insert(self.quas_key, OrbType::Quas);
+        keybindings.insert(self.wex_key, OrbType::Wex);
+        keybindings.insert(self.exort_key, OrbType::Exort);
+        keybindings.insert(self.invoke_key, OrbType::Invoke);
+        keybindings.insert(self.first_slot_key, OrbType::FirstSlot);
+        keybindings.insert(self.second_slot_key, OrbType::SecondSlot);
+        keybindings
+    }
 
+    // Same settings for a different window, e.g. to play back a replay at the size it was recorded
+    pub fn with_window_size(&self, window_width: f32, window_height: f32) -> Self {
         Self {
-            window_width: user_settings.window_width,
-            window_height: user_settings.window_height,
-            background_draw_param,
-            font_size,
-            quas_key,
-            wex_key,
-            exort_key,
-            invoke_key,
+            window_width,
+            window_height,
+            background_draw_param: Self::background_draw_param(window_width, window_height),
+            font_size: Self::font_size(window_width, window_height),
+            ..self.clone()
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        Layout::new(self.window_width, self.window_height)
+    }
+
+    fn font_size(window_width: f32, window_height: f32) -> f32 {
+        Layout::new(window_width, window_height).font_size
+    }
+
+    // The background image is stretched over the whole window
+    fn background_draw_param(window_width: f32, window_height: f32) -> graphics::DrawParam {
+        let (image_width, image_height) = png_size(BACKGROUND_IMAGE);
+        let (scale_x, scale_y) =
+            Layout::new(window_width, window_height).fill_scale(image_width, image_height);
+        graphics::DrawParam::new().scale(Vec2::new(scale_x, scale_y))
+    }
+
+    /// Checks the values read from settings.toml, every one that can't be used is
+    /// replaced by its default.
+    fn validate(user_settings: UserSettings) -> (Self, Vec<String>) {
+        let defaults = UserSettings::default();
+        let mut problems = Vec::new();
+
+        let mut dimension = |name: &str, value: f32, range: RangeInclusive<f32>, default: f32| {
+            if range.contains(&value) {
+                value
+            } else {
+                problems.push(format!(
+                    "{} {} is not between {} and {}, using {}",
+                    name,
+                    value,
+                    range.start(),
+                    range.end(),
+                    default
+                ));
+                default
+            }
+        };
+        let window_width = dimension(
+            "window_width",
+            user_settings.window_width,
+            WINDOW_WIDTHS,
+            defaults.window_width,
+        );
+        let window_height = dimension(
+            "window_height",
+            user_settings.window_height,
+            WINDOW_HEIGHTS,
+            defaults.window_height,
+        );
+
+        let mut volume = |name: &str, value: u32, default: u32| {
+            if value <= MAX_VOLUME {
+                value
+            } else {
+                problems.push(format!(
+                    "audio.{} {} is above {}, using {}",
+                    name, value, MAX_VOLUME, default
+                ));
+                default
+            }
+        };
+        let audio = AudioSettings {
+            master: volume("master", user_settings.audio.master, defaults.audio.master),
+            effects: volume(
+                "effects",
+                user_settings.audio.effects,
+                defaults.audio.effects,
+            ),
+            music: volume("music", user_settings.audio.music, defaults.audio.music),
+        };
+
+        let time_limit = if TIME_LIMITS.contains(&user_settings.time_limit) {
+            user_settings.time_limit
+        } else {
+            problems.push(format!(
+                "time_limit {} is not 30, 60 or 120, using {}",
+                user_settings.time_limit, defaults.time_limit
+            ));
+            defaults.time_limit
+        };
+
+        let fields = [
+            ("quas_key", &user_settings.quas_key, &defaults.quas_key),
+            ("wex_key", &user_settings.wex_key, &defaults.wex_key),
+            ("exort_key", &user_settings.exort_key, &defaults.exort_key),
+            (
+                "invoke_key",
+                &user_settings.invoke_key,
+                &defaults.invoke_key,
+            ),
+            (
+                "first_slot_key",
+                &user_settings.first_slot_key,
+                &defaults.first_slot_key,
+            ),
+            (
+                "second_slot_key",
+                &user_settings.second_slot_key,
+                &defaults.second_slot_key,
+            ),
+        ];
+        let default_binding =
+            |default: &str| Binding::parse(default).expect("Default bindings are valid");
+
+        let mut bindings = Vec::with_capacity(fields.len());
+        for (name, binding, default) in fields {
+            let binding = match Binding::parse(binding) {
+                Ok(binding) if binding.is_bindable() => binding,
+                Ok(binding) => {
+                    problems.push(format!(
+                        "{}: {} can't be bound, using {}",
+                        name, binding, default
+                    ));
+                    default_binding(default)
+                }
+                Err(e) => {
+                    problems.push(format!("{}: {}, using {}", name, e, default));
+                    default_binding(default)
+                }
+            };
+
+            // The first orb keeps a binding that is used twice
+            if let Some(index) = bindings.iter().position(|other| *other == binding) {
+                problems.push(format!(
+                    "{} is bound to {} like {}, using {}",
+                    name, binding, fields[index].0, default
+                ));
+                bindings.push(default_binding(default));
+            } else {
+                bindings.push(binding);
+            }
+        }
+
+        let mut settings = Self {
+            window_width,
+            window_height,
+            background_draw_param: Self::background_draw_param(window_width, window_height),
+            font_size: Self::font_size(window_width, window_height),
+            quas_key: bindings[0],
+            wex_key: bindings[1],
+            exort_key: bindings[2],
+            invoke_key: bindings[3],
+            first_slot_key: bindings[4],
+            second_slot_key: bindings[5],
+            adaptive_spawning: user_settings.adaptive_spawning,
+            time_limit,
+            hud: user_settings.hud,
+            audio,
+            spell_book: SpellBook::default(),
+            combo_book: ComboBook::default(),
+            path: UserSettings::get_settings_path(),
+            overrides: None,
+        };
+
+        // A default can still collide with a binding that was kept
+        if let Some(conflict) = settings.binding_conflict() {
+            problems.push(format!("{}, using the default bindings", conflict));
+            settings.quas_key = default_binding(&defaults.quas_key);
+            settings.wex_key = default_binding(&defaults.wex_key);
+            settings.exort_key = default_binding(&defaults.exort_key);
+            settings.invoke_key = default_binding(&defaults.invoke_key);
+            settings.first_slot_key = default_binding(&defaults.first_slot_key);
+            settings.second_slot_key = default_binding(&defaults.second_slot_key);
+        }
+
+        (settings, problems)
+    }
+
+    // The keys of a hint with these bindings, e.g. "D or Q W R or W Q R"
+    pub fn hint_text(&self, hint: &Hint) -> String {
+        let mut options = Vec::new();
+        if let Some(slot) = hint.slot {
+            options.push([self.first_slot_key, self.second_slot_key][slot].to_string());
+        }
+        for orbs in &hint.orbs {
+            let mut keys: Vec<String> = orbs
+                .iter()
+                .map(|orb| match orb {
+                    'Q' => self.quas_key.to_string(),
+                    'W' => self.wex_key.to_string(),
+                    _ => self.exort_key.to_string(),
+                })
+                .collect();
+            keys.push(self.invoke_key.to_string());
+            options.push(keys.join(" "));
         }
+        options.join(" or ")
+    }
+
+    // Short description of the bindings a run was played with, e.g. "Q W E R"
+    pub fn keybinding_profile(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.quas_key, self.wex_key, self.exort_key, self.invoke_key
+        )
     }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 struct UserSettings {
+    version: u32,
     window_width: f32,
     window_height: f32,
     quas_key: String,
     wex_key: String,
     exort_key: String,
     invoke_key: String,
+    first_slot_key: String,
+    second_slot_key: String,
+    // Spawn the spells the player struggles with more often
+    #[serde(default)]
+    adaptive_spawning: bool,
+    // Seconds of a time attack run
+    time_limit: u32,
+    // Tables, so they have to come after the plain values
+    #[serde(default)]
+    hud: HudSettings,
+    #[serde(default)]
+    audio: AudioSettings,
 }
 
 impl Default for UserSettings {
     fn default() -> Self {
         Self {
+            version: SETTINGS_VERSION,
             window_width: 1024.0,
             window_height: 1024.0,
             quas_key: String::from("Q"),
             wex_key: String::from("W"),
             exort_key: String::from("E"),
             invoke_key: String::from("R"),
+            first_slot_key: String::from("D"),
+            second_slot_key: String::from("F"),
+            adaptive_spawning: false,
+            time_limit: 60,
+            hud: HudSettings::default(),
+            audio: AudioSettings::default(),
+        }
+    }
+}
+
+impl From<&Settings> for UserSettings {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            window_width: settings.window_width,
+            window_height: settings.window_height,
+            quas_key: settings.quas_key.to_string(),
+            wex_key: settings.wex_key.to_string(),
+            exort_key: settings.exort_key.to_string(),
+            invoke_key: settings.invoke_key.to_string(),
+            first_slot_key: settings.first_slot_key.to_string(),
+            second_slot_key: settings.second_slot_key.to_string(),
+            adaptive_spawning: settings.adaptive_spawning,
+            time_limit: settings.time_limit,
+            hud: settings.hud,
+            audio: settings.audio,
         }
     }
 }
 
 impl UserSettings {
-    fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let path = Self::get_settings_path();
-        println!("Settings path is {}", path.to_str().unwrap());
-        if std::path::Path::exists(&path) {
-            Ok(Self::load()?)
-        } else {
+    fn new(path: &Path) -> (Self, Vec<String>) {
+        println!("Settings path is {}", path.display());
+
+        if !path.exists() {
             let settings = Self::default();
-            settings.save()?;
-            Ok(settings)
+            let mut problems = Vec::new();
+            if let Err(e) = settings.save(path) {
+                problems.push(format!("Could not create {}: {}", path.display(), e));
+            }
+            return (settings, problems);
+        }
+
+        match Self::load(path) {
+            Ok(loaded) => loaded,
+            Err(e) => (
+                Self::default(),
+                vec![format!(
+                    "Could not read {}, using the default settings: {}",
+                    path.display(),
+                    e
+                )],
+            ),
+        }
+    }
+
+    fn load(path: &Path) -> Result<(Self, Vec<String>), Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut table: toml::value::Table = toml::from_str(&contents)?;
+        let mut problems = Vec::new();
+
+        match Self::migrate(&mut table) {
+            Ok(Some(version)) => {
+                if let Err(e) = Self::upgrade_file(path, &contents, &table, version) {
+                    problems.push(format!("Could not upgrade {}: {}", path.display(), e));
+                }
+            }
+            Ok(None) => {}
+            Err(e) => problems.push(e),
+        }
+
+        let (settings, invalid) = Self::from_table(table);
+        problems.extend(invalid);
+        Ok((settings, problems))
+    }
+
+    /// Brings the table up to the current version, returns the version it had
+    /// if anything was changed.
+    fn migrate(table: &mut toml::value::Table) -> Result<Option<u32>, String> {
+        // Files from before versions were added have none
+        let version = match table.get("version") {
+            None => 0,
+            Some(toml::Value::Integer(version)) if *version >= 0 => *version as u32,
+            Some(version) => return Err(format!("Settings version {} is not valid", version)),
+        };
+
+        if version > SETTINGS_VERSION {
+            return Err(format!(
+                "settings.toml is from a newer version of the game ({}), settings this one doesn't know are ignored",
+                version
+            ));
         }
+        if version == SETTINGS_VERSION {
+            return Ok(None);
+        }
+
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(table);
+        }
+        table.insert(
+            String::from("version"),
+            toml::Value::Integer(SETTINGS_VERSION.into()),
+        );
+        Ok(Some(version))
+    }
+
+    // The old file is kept next to the upgraded one, e.g. settings.v0.toml.bak
+    fn upgrade_file(
+        path: &Path,
+        contents: &str,
+        table: &toml::value::Table,
+        version: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // A plain table keeps its key order, so a table like [hud] can come before plain
+        // values and fail to serialize. As a value the plain values are written first
+        let upgraded = toml::to_string(&toml::Value::Table(table.clone()))?;
+        let backup = path.with_extension(format!("v{}.toml.bak", version));
+        fs::write(&backup, contents)?;
+        fs::write(path, upgraded)?;
+        println!(
+            "Upgraded {} to version {}, the old one is {}",
+            path.display(),
+            SETTINGS_VERSION,
+            backup.display()
+        );
+        Ok(())
     }
 
-    fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        let path = Self::get_settings_path();
-        let mut file = fs::File::open(&path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
+    // Every field is read on its own so one broken value doesn't reset the others
+    fn from_table(mut table: toml::value::Table) -> (Self, Vec<String>) {
+        let defaults = Self::default();
+        let mut problems = Vec::new();
+        // Already checked by migrate
+        table.remove("version");
+
+        let settings = Self {
+            version: SETTINGS_VERSION,
+            window_width: Self::field(
+                &mut table,
+                "window_width",
+                defaults.window_width,
+                &mut problems,
+            ),
+            window_height: Self::field(
+                &mut table,
+                "window_height",
+                defaults.window_height,
+                &mut problems,
+            ),
+            quas_key: Self::field(&mut table, "quas_key", defaults.quas_key, &mut problems),
+            wex_key: Self::field(&mut table, "wex_key", defaults.wex_key, &mut problems),
+            exort_key: Self::field(&mut table, "exort_key", defaults.exort_key, &mut problems),
+            invoke_key: Self::field(&mut table, "invoke_key", defaults.invoke_key, &mut problems),
+            first_slot_key: Self::field(
+                &mut table,
+                "first_slot_key",
+                defaults.first_slot_key,
+                &mut problems,
+            ),
+            second_slot_key: Self::field(
+                &mut table,
+                "second_slot_key",
+                defaults.second_slot_key,
+                &mut problems,
+            ),
+            adaptive_spawning: Self::field(
+                &mut table,
+                "adaptive_spawning",
+                defaults.adaptive_spawning,
+                &mut problems,
+            ),
+            time_limit: Self::field(&mut table, "time_limit", defaults.time_limit, &mut problems),
+            hud: Self::field(&mut table, "hud", defaults.hud, &mut problems),
+            audio: Self::field(&mut table, "audio", defaults.audio, &mut problems),
+        };
+
+        // Whatever is left is most likely a typo in a name
+        for name in table.keys() {
+            problems.push(format!("Unknown setting {} is ignored", name));
+        }
+
+        (settings, problems)
+    }
 
-        // If the toml is in the wrong format fall back to default
-        match toml::from_str(&contents) {
-            Ok(settings) => Ok(settings),
-            Err(_) => Ok(Self::default()),
+    // A missing field is fine, the default is used without a word
+    fn field<T: DeserializeOwned>(
+        table: &mut toml::value::Table,
+        name: &str,
+        default: T,
+        problems: &mut Vec<String>,
+    ) -> T {
+        match table.remove(name) {
+            Some(value) => value.try_into().unwrap_or_else(|e| {
+                problems.push(format!("{} is invalid, using the default: {}", name, e));
+                default
+            }),
+            None => default,
         }
     }
 
-    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+    fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(config_dir) = path.parent() {
+            fs::create_dir_all(config_dir)?;
+        }
+
         let contents = toml::to_string(self)?;
-        let mut file = fs::File::create(Self::get_settings_path())?;
-        Ok(file.write_all(&contents.as_bytes())?)
+        let mut file = fs::File::create(path)?;
+        Ok(file.write_all(contents.as_bytes())?)
     }
 
     fn get_settings_path() -> PathBuf {
         let project_dirs =
             ProjectDirs::from("", "", "Invoke").expect("Home directory must be present");
 
-        let config_dir = project_dirs.config_dir();
-        fs::create_dir_all(config_dir).expect("Should be able to create dirs");
-        config_dir.join("settings.toml")
+        project_dirs.config_dir().join("settings.toml")
+    }
+}
+
+// Before versions were added adaptive_spawning could be left out, now every setting is written
+fn migrate_v0_to_v1(table: &mut toml::value::Table) {
+    table
+        .entry("adaptive_spawning")
+        .or_insert(toml::Value::Boolean(false));
+}
+
+// Spell slots came with version 2, they get D and F unless those already press an orb
+fn migrate_v1_to_v2(table: &mut toml::value::Table) {
+    let mut taken: Vec<Binding> = ["quas_key", "wex_key", "exort_key", "invoke_key"]
+        .iter()
+        .filter_map(|name| table.get(*name)?.as_str())
+        .filter_map(|binding| Binding::parse(binding).ok())
+        .collect();
+
+    let mut free_keys = ["D", "F", "G", "H", "C", "V", "Z", "X"]
+        .into_iter()
+        .filter_map(|key| Binding::parse(key).ok());
+    for name in ["first_slot_key", "second_slot_key"] {
+        if table.contains_key(name) {
+            continue;
+        }
+        if let Some(binding) = free_keys.find(|binding| !taken.contains(binding)) {
+            table.insert(String::from(name), toml::Value::String(binding.to_string()));
+            taken.push(binding);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ggez::input::keyboard::{KeyCode, KeyMods};
+
+    fn parse(contents: &str) -> (UserSettings, Vec<String>, Option<u32>) {
+        let mut table = toml::from_str(contents).unwrap();
+        let migrated = UserSettings::migrate(&mut table).unwrap();
+        let (settings, problems) = UserSettings::from_table(table);
+        (settings, problems, migrated)
+    }
+
+    #[test]
+    fn test_books_are_read_next_to_the_settings_file() {
+        let dir = std::env::temp_dir().join(format!("invoke-books-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("spells.toml"), "spells = []").unwrap();
+        fs::write(dir.join("combos.toml"), "combos = []").unwrap();
+
+        let (settings, problems) = Settings::load(Some(&dir.join("other.toml")));
+        assert_eq!(settings.spell_book, SpellBook::default());
+        let spells = dir.join("spells.toml").display().to_string();
+        let combos = dir.join("combos.toml").display().to_string();
+        assert!(problems.iter().any(|problem| problem.contains(&spells)));
+        assert!(problems.iter().any(|problem| problem.contains(&combos)));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_binding_conflict() {
+        let mut settings = Settings::validate(UserSettings::default()).0;
+        assert_eq!(settings.binding_conflict(), None);
+
+        settings.invoke_key = Binding::from_key(KeyCode::W, KeyMods::empty());
+        assert_eq!(
+            settings.binding_conflict(),
+            Some(String::from("Wex and Invoke are both bound to W"))
+        );
+    }
+
+    #[test]
+    fn test_overrides_are_not_saved() {
+        let file = Settings::validate(UserSettings::default()).0;
+        let mut session = file.with_window_size(1280.0, 720.0);
+        session.quas_key = Binding::from_key(KeyCode::Space, KeyMods::CTRL);
+        let mut edited = session.overriding(&file);
+
+        // Changed in the settings screen, so it is saved
+        edited.wex_key = Binding::from_key(KeyCode::T, KeyMods::empty());
+
+        let saved = edited.to_user_settings();
+        let defaults = UserSettings::default();
+        assert_eq!(saved.quas_key, defaults.quas_key);
+        assert_eq!(saved.window_width, defaults.window_width);
+        assert_eq!(saved.window_height, defaults.window_height);
+        assert_eq!(saved.wex_key, "T");
+
+        // A resized window is saved even if the command line set the size
+        let resized = edited.with_window_size(1600.0, 900.0).to_user_settings();
+        assert_eq!(resized.window_width, 1600.0);
+        assert_eq!(resized.quas_key, defaults.quas_key);
+    }
+
+    #[test]
+    fn test_hint_text() {
+        let mut settings = Settings::validate(UserSettings::default()).0;
+        settings.invoke_key = Binding::from_key(KeyCode::Space, KeyMods::empty());
+
+        let hint = Hint {
+            slot: Some(1),
+            orbs: vec![vec!['Q', 'E'], vec!['E', 'Q']],
+        };
+        assert_eq!(settings.hint_text(&hint), "F or Q E Space or E Q Space");
+    }
+
+    #[test]
+    fn test_user_settings_roundtrip() {
+        let mut settings = Settings::validate(UserSettings::default()).0;
+        settings.quas_key = Binding::from_key(KeyCode::A, KeyMods::empty());
+        settings.wex_key = Binding::from_key(KeyCode::Space, KeyMods::CTRL);
+        settings.adaptive_spawning = true;
+
+        let user_settings = UserSettings::from(&settings);
+        assert_eq!(user_settings.quas_key, "A");
+        assert_eq!(user_settings.wex_key, "Ctrl+Space");
+        assert!(user_settings.adaptive_spawning);
+
+        let (settings, problems) = Settings::validate(user_settings);
+        assert!(problems.is_empty());
+        assert_eq!(
+            settings.quas_key,
+            Binding::from_key(KeyCode::A, KeyMods::empty())
+        );
+        assert_eq!(
+            settings.wex_key,
+            Binding::from_key(KeyCode::Space, KeyMods::CTRL)
+        );
+        assert!(Binding::from_key(KeyCode::D, KeyMods::empty()).is_bindable());
+        assert!(!Binding::from_key(KeyCode::Escape, KeyMods::empty()).is_bindable());
+    }
+
+    #[test]
+    fn test_unknown_binding_falls_back() {
+        let user_settings = UserSettings {
+            quas_key: String::from("NotAKey"),
+            wex_key: String::from("Mouse4"),
+            exort_key: String::from("Escape"),
+            ..UserSettings::default()
+        };
+
+        let (settings, problems) = Settings::validate(user_settings);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(
+            settings.quas_key,
+            Binding::from_key(KeyCode::Q, KeyMods::empty())
+        );
+        assert_eq!(settings.wex_key.to_string(), "Mouse4");
+        assert_eq!(
+            settings.exort_key,
+            Binding::from_key(KeyCode::E, KeyMods::empty())
+        );
+    }
+
+    #[test]
+    fn test_broken_fields_fall_back() {
+        let contents = r#"
+            window_width = "wide"
+            window_height = 768
+            quas_key = "D"
+            wex_kye = "F"
+        "#;
+
+        let (user_settings, problems, _) = parse(contents);
+        assert_eq!(user_settings.window_width, 1024.0);
+        assert_eq!(user_settings.window_height, 768.0);
+        assert_eq!(user_settings.quas_key, "D");
+        assert_eq!(user_settings.wex_key, "W");
+        assert_eq!(problems.len(), 2);
+        assert!(problems[1].contains("wex_kye"));
+    }
+
+    #[test]
+    fn test_hud_table() {
+        let contents = "quas_key = \"D\"\n\n[hud]\npreview = false\n";
+        let (user_settings, problems, _) = parse(contents);
+        assert!(problems.is_empty());
+        assert!(!user_settings.hud.preview);
+        assert!(user_settings.hud.score);
+
+        let written = toml::to_string(&user_settings).unwrap();
+        assert!(written.ends_with(
+            "[hud]\nscore = true\ntime = true\nspeed = true\nstreak = true\npreview = false\n\
+             hint = false\n\n\
+             [audio]\nmaster = 80\neffects = 100\nmusic = 50\n"
+        ));
+    }
+
+    #[test]
+    fn test_audio_table() {
+        let contents = "[audio]\nmusic = 0\neffects = 150\n";
+        let (user_settings, problems, _) = parse(contents);
+        assert!(problems.is_empty());
+        assert_eq!(user_settings.audio.master, 80);
+        assert_eq!(user_settings.audio.music, 0);
+
+        let (settings, problems) = Settings::validate(user_settings);
+        assert_eq!(settings.audio.effects, 100);
+        assert_eq!(problems, ["audio.effects 150 is above 100, using 100"]);
+    }
+
+    #[test]
+    fn test_validation() {
+        let user_settings = UserSettings {
+            window_width: 10.0,
+            window_height: 720.0,
+            wex_key: String::from("Q"),
+            exort_key: String::from("Q"),
+            time_limit: 45,
+            ..UserSettings::default()
+        };
+
+        let (settings, problems) = Settings::validate(user_settings);
+        assert_eq!(settings.window_width, 1024.0);
+        assert_eq!(settings.window_height, 720.0);
+        assert_eq!(settings.time_limit, 60);
+        assert_eq!(settings.keybinding_profile(), "Q W E R");
+        assert_eq!(problems.len(), 4);
+        assert_eq!(problems[1], "time_limit 45 is not 30, 60 or 120, using 60");
+        assert_eq!(problems[2], "wex_key is bound to Q like quas_key, using W");
+
+        // Wex goes back to W, which Quas already uses, so all bindings are reset
+        let user_settings = UserSettings {
+            quas_key: String::from("W"),
+            wex_key: String::from("W"),
+            exort_key: String::from("A"),
+            ..UserSettings::default()
+        };
+        let (settings, problems) = Settings::validate(user_settings);
+        assert_eq!(settings.keybinding_profile(), "Q W E R");
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[test]
+    fn test_migrate_v0_letters() {
+        // Written by the first release: letters only, no adaptive spawning
+        let (user_settings, problems, migrated) =
+            parse(include_str!("../tests/fixtures/settings/v0_letters.toml"));
+        assert_eq!(migrated, Some(0));
+        assert!(problems.is_empty());
+        assert_eq!(user_settings.version, SETTINGS_VERSION);
+        assert_eq!(user_settings.quas_key, "A");
+        assert_eq!(user_settings.invoke_key, "F");
+        assert!(!user_settings.adaptive_spawning);
+
+        // D and F already press orbs, so the spell slots get the next free keys
+        assert_eq!(user_settings.first_slot_key, "G");
+        assert_eq!(user_settings.second_slot_key, "H");
+        assert!(Settings::validate(user_settings).1.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v0_adaptive_spawning() {
+        let (user_settings, problems, migrated) = parse(include_str!(
+            "../tests/fixtures/settings/v0_adaptive_spawning.toml"
+        ));
+        assert_eq!(migrated, Some(0));
+        assert!(problems.is_empty());
+        assert_eq!(user_settings.window_width, 1280.0);
+        assert!(user_settings.adaptive_spawning);
+        assert_eq!(user_settings.first_slot_key, "D");
+        assert_eq!(user_settings.second_slot_key, "F");
+    }
+
+    #[test]
+    fn test_migrate_v0_any_binding() {
+        let (user_settings, problems, migrated) = parse(include_str!(
+            "../tests/fixtures/settings/v0_any_binding.toml"
+        ));
+        assert_eq!(migrated, Some(0));
+        assert!(problems.is_empty());
+
+        let (settings, problems) = Settings::validate(user_settings);
+        assert!(problems.is_empty());
+        assert_eq!(settings.keybinding_profile(), "Q W MouseRight Ctrl+Space");
+    }
+
+    #[test]
+    fn test_migrate_v1_default() {
+        let (user_settings, problems, migrated) =
+            parse(include_str!("../tests/fixtures/settings/v1_default.toml"));
+        assert_eq!(migrated, Some(1));
+        assert!(problems.is_empty());
+        assert_eq!(user_settings.version, SETTINGS_VERSION);
+        assert_eq!(user_settings.first_slot_key, "D");
+        assert_eq!(user_settings.second_slot_key, "F");
+
+        let (settings, problems) = Settings::validate(user_settings);
+        assert!(problems.is_empty());
+        assert_eq!(settings.binding_conflict(), None);
+    }
+
+    #[test]
+    fn test_migrate_v1_orbs_on_d_f() {
+        // Quas and Wex already on D and F, the spell slots must not take them
+        let (user_settings, problems, migrated) = parse(include_str!(
+            "../tests/fixtures/settings/v1_orbs_on_d_f.toml"
+        ));
+        assert_eq!(migrated, Some(1));
+        assert!(problems.is_empty());
+        assert_eq!(user_settings.quas_key, "D");
+        assert_eq!(user_settings.wex_key, "F");
+        assert_eq!(user_settings.first_slot_key, "H");
+        assert_eq!(user_settings.second_slot_key, "C");
+        assert!(user_settings.adaptive_spawning);
+        assert!(!user_settings.hud.time);
+        assert_eq!(user_settings.audio.music, 0);
+
+        let (settings, problems) = Settings::validate(user_settings);
+        assert!(problems.is_empty());
+        assert_eq!(settings.binding_conflict(), None);
+    }
+
+    #[test]
+    fn test_current_version_is_not_migrated() {
+        let contents = toml::to_string(&UserSettings::default()).unwrap();
+        let (_, problems, migrated) = parse(&contents);
+        assert_eq!(migrated, None);
+        assert!(problems.is_empty());
+
+        let mut table = toml::from_str("version = 99").unwrap();
+        assert!(UserSettings::migrate(&mut table).is_err());
+    }
+
+    #[test]
+    fn test_upgrade_file_keeps_backup() {
+        let dir = std::env::temp_dir().join(format!("invoke-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.toml");
+        let contents = include_str!("../tests/fixtures/settings/v0_letters.toml");
+        fs::write(&path, contents).unwrap();
+
+        let (user_settings, problems) = UserSettings::load(&path).unwrap();
+        assert!(problems.is_empty());
+        assert_eq!(user_settings.quas_key, "A");
+
+        let backup = fs::read_to_string(dir.join("settings.v0.toml.bak")).unwrap();
+        assert_eq!(backup, contents);
+        let upgraded = fs::read_to_string(&path).unwrap();
+        assert!(upgraded.contains("version = 2"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_v1_file_with_tables() {
+        let dir = std::env::temp_dir().join(format!("invoke-settings-v1-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.toml");
+        let contents = include_str!("../tests/fixtures/settings/v1_orbs_on_d_f.toml");
+        fs::write(&path, contents).unwrap();
+
+        let (_, problems) = UserSettings::load(&path).unwrap();
+        assert!(problems.is_empty(), "{:?}", problems);
+
+        let backup = fs::read_to_string(dir.join("settings.v1.toml.bak")).unwrap();
+        assert_eq!(backup, contents);
+
+        let upgraded: toml::value::Table =
+            toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(upgraded["version"].as_integer(), Some(2));
+        assert_eq!(upgraded["hud"]["time"].as_bool(), Some(false));
+        assert_eq!(upgraded["audio"]["master"].as_integer(), Some(60));
+        assert_eq!(upgraded["first_slot_key"].as_str(), Some("H"));
+
+        // Already upgraded, so loading again leaves the backup alone
+        fs::remove_file(dir.join("settings.v1.toml.bak")).unwrap();
+        let (_, problems) = UserSettings::load(&path).unwrap();
+        assert!(problems.is_empty());
+        assert!(!dir.join("settings.v1.toml.bak").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,1499 @@
+use crate::combo_book::{Combo, ComboBook, ComboResult};
+use crate::grades::{Grade, Grades};
+use crate::highscores::GameMode;
+use crate::input_buffer::InputBuffer;
+use crate::layout::Layout;
+use crate::orbs::OrbType;
+use crate::run_history::RunEnd;
+use crate::spawner::Spawner;
+use crate::spell_book::{SpellBook, SpellDefinition};
+use crate::spells::{Position, Spell, SpellType};
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// Spells fall in pixels per second, starting at `initial_speed` and getting
+// `speed_ramp` pixels per second faster every second
+struct Rules {
+    initial_speed: f32,
+    speed_ramp: f32,
+    spawn_interval: Duration,
+}
+
+const NORMAL_RULES: Rules = Rules {
+    initial_speed: 30.0,
+    speed_ramp: 30.0,
+    spawn_interval: Duration::from_secs(1),
+};
+
+// All spells fall at the same speed and only one is on the screen at a time
+const PRO_RULES: Rules = Rules {
+    initial_speed: 18.0,
+    speed_ramp: 9.0,
+    spawn_interval: Duration::from_secs(2),
+};
+
+// Before the first invoke the buffer is empty, so three orbs and the invoke are needed
+const FIRST_SPELL_KEY_PRESSES: usize = 4;
+
+// Graded Pro mode: wasted presses a run can afford, a wrong spell costs extra on top
+const PENALTY_BUDGET: usize = 10;
+const WRONG_SPELL_PENALTY: usize = 3;
+
+// Time attack: how long a run lasts unless it is set, and what a wrong cast takes off it
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(60);
+const WRONG_CAST_PENALTY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    SpellSpawned {
+        id: usize,
+        spell: SpellType,
+        at: Duration,
+    },
+    OrbPressed(char),
+    SpellCast {
+        id: usize,
+        spell: SpellType,
+        // Where the spell was when it was cleared
+        position: Position,
+        at: Duration,
+    },
+    GameOver(RunEnd),
+}
+
+/// The fewest presses that cast the current Pro mode spell, counted from the last cast.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+    // Slot the spell was still in, its key alone casts it
+    pub slot: Option<usize>,
+    // Orbs to press before the invoke, every order that takes the fewest presses
+    pub orbs: Vec<Vec<char>>,
+}
+
+/// The combo that is drilled right now, from its first spell showing up to its last one cast.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComboProgress {
+    pub combo: Combo,
+    // Spells of it that have been cast
+    pub cast: usize,
+    pub started: Duration,
+    pub key_presses: usize,
+    // For the whole chain, from the buffer and slots it started with
+    pub fewest_key_presses: usize,
+}
+
+/// How a time attack run went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeAttackResult {
+    pub time_limit: Duration,
+    pub spells: usize,
+    pub key_presses: usize,
+    pub errors: usize,
+}
+
+impl TimeAttackResult {
+    pub fn spells_per_minute(&self) -> f32 {
+        self.spells as f32 * 60.0 / self.time_limit.as_secs_f32()
+    }
+
+    // Presses of wrong casts included, `None` before the first spell
+    pub fn keys_per_spell(&self) -> Option<f32> {
+        (self.spells > 0).then(|| self.key_presses as f32 / self.spells as f32)
+    }
+
+    /// One line for the game over screen, e.g. "42 spells in 60s, 3.4 keys per spell, 2 errors".
+    pub fn summary(&self) -> String {
+        let keys_per_spell = match self.keys_per_spell() {
+            Some(keys) => format!(", {:.1} keys per spell", keys),
+            None => String::new(),
+        };
+        format!(
+            "{} spells in {}s{}, {} error{}",
+            self.spells,
+            self.time_limit.as_secs(),
+            keys_per_spell,
+            self.errors,
+            if self.errors == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// The rules of a run without anything ggez related: orb presses and time go in, events come out.
+pub struct Simulation {
+    mode: GameMode,
+    rules: &'static Rules,
+    rng: StdRng,
+    spawner: Spawner,
+    spell_book: SpellBook,
+    // Layout of the window the run is played in, spells move in its pixels
+    layout: Layout,
+    spells: Vec<Spell>,
+    spawned: usize,
+    input_buffer: InputBuffer,
+    score: usize,
+    speed: f32,
+    elapsed: Duration,
+    last_spell_time: Duration,
+    end: Option<RunEnd>,
+    // Events that have not been handed out yet
+    events: Vec<Event>,
+    // Pro mode only: the spell that shows up once the current one is cast
+    next_spell: Option<Spell>,
+    required_key_presses: usize,
+    current_key_presses: usize,
+    // Buffer the last spell was invoked with, empty before the first one
+    cast_buffer: Vec<char>,
+    // The last two invoked spells, the most recent one first. They can be cast
+    // again with a slot key instead of another invoke
+    slots: [Option<SpellType>; 2],
+    // Slots when the last spell was cast
+    cast_slots: [Option<SpellType>; 2],
+    // Spells cast in a row without a wasted key press
+    streak: usize,
+    // Graded Pro mode only: what is left of the penalty budget and how every spell went
+    budget: usize,
+    grades: Grades,
+    // Combo drill only: the combos to pick from, the ones with spells on the screen or
+    // coming up, the current one first, and the spells of the last one still to come
+    combo_book: ComboBook,
+    combo_queue: VecDeque<usize>,
+    combo_spells: VecDeque<SpellType>,
+    combo: Option<ComboProgress>,
+    combo_results: Vec<ComboResult>,
+    // Time attack only: the length of the run, the time taken off it by wrong casts,
+    // how many there were and every key press of the run
+    time_limit: Duration,
+    penalty: Duration,
+    errors: usize,
+    total_key_presses: usize,
+}
+
+impl Simulation {
+    pub fn new(
+        mode: GameMode,
+        seed: u64,
+        spawner: Spawner,
+        spell_book: SpellBook,
+        combo_book: ComboBook,
+        window_width: f32,
+        window_height: f32,
+    ) -> Self {
+        let rules = match mode {
+            GameMode::Pro | GameMode::GradedPro | GameMode::Combos => &PRO_RULES,
+            GameMode::Normal | GameMode::Daily | GameMode::Slots | GameMode::TimeAttack => {
+                &NORMAL_RULES
+            }
+        };
+
+        let mut simulation = Self {
+            mode,
+            rules,
+            rng: StdRng::seed_from_u64(seed),
+            spawner,
+            spell_book,
+            layout: Layout::new(window_width, window_height),
+            spells: Vec::new(),
+            spawned: 0,
+            input_buffer: InputBuffer::default(),
+            score: 0,
+            speed: rules.initial_speed,
+            elapsed: Duration::ZERO,
+            last_spell_time: Duration::ZERO,
+            end: None,
+            events: Vec::new(),
+            next_spell: None,
+            required_key_presses: FIRST_SPELL_KEY_PRESSES,
+            current_key_presses: 0,
+            cast_buffer: Vec::new(),
+            slots: [None, None],
+            cast_slots: [None, None],
+            streak: 0,
+            budget: PENALTY_BUDGET,
+            grades: Grades::default(),
+            combo_book,
+            combo_queue: VecDeque::new(),
+            combo_spells: VecDeque::new(),
+            combo: None,
+            combo_results: Vec::new(),
+            time_limit: DEFAULT_TIME_LIMIT,
+            penalty: Duration::ZERO,
+            errors: 0,
+            total_key_presses: 0,
+        };
+
+        // Pro mode starts with a spell on the screen and already knows the one after it
+        if mode.is_pro() {
+            let initial_spell = simulation.new_spell(None);
+            let next_spell = simulation.new_spell(Some(&initial_spell.spell_type));
+            simulation.spawn(initial_spell);
+            simulation.next_spell = Some(next_spell);
+            simulation.combo = simulation.start_combo();
+        }
+        if mode == GameMode::TimeAttack {
+            simulation.spawn_in_slot(None);
+        }
+
+        simulation
+    }
+
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn spells(&self) -> &[Spell] {
+        &self.spells
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    // Orbs currently in the buffer, oldest first
+    pub fn buffer(&self) -> &[char] {
+        &self.input_buffer.buffer
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn streak(&self) -> usize {
+        self.streak
+    }
+
+    pub fn slots(&self) -> &[Option<SpellType>; 2] {
+        &self.slots
+    }
+
+    // Wasted presses the run can still afford, only graded Pro runs have a budget
+    pub fn budget(&self) -> Option<usize> {
+        (self.mode == GameMode::GradedPro).then_some(self.budget)
+    }
+
+    pub fn grades(&self) -> &Grades {
+        &self.grades
+    }
+
+    // What is left of a time attack run
+    pub fn time_left(&self) -> Option<Duration> {
+        (self.mode == GameMode::TimeAttack)
+            .then(|| self.time_limit.saturating_sub(self.elapsed + self.penalty))
+    }
+
+    // Wrong casts of a time attack run
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    pub fn time_attack_result(&self) -> TimeAttackResult {
+        TimeAttackResult {
+            time_limit: self.time_limit,
+            spells: self.score,
+            key_presses: self.total_key_presses,
+            errors: self.errors,
+        }
+    }
+
+    pub fn combo(&self) -> Option<&ComboProgress> {
+        self.combo.as_ref()
+    }
+
+    pub fn combo_results(&self) -> &[ComboResult] {
+        &self.combo_results
+    }
+
+    // Presses since the last spell was cast
+    pub fn key_presses(&self) -> usize {
+        self.current_key_presses
+    }
+
+    /// How the current Pro mode spell could have been cast from where the last one left off.
+    pub fn hint(&self) -> Option<Hint> {
+        if !self.mode.is_pro() {
+            return None;
+        }
+        let current = self.spells.first()?;
+
+        let slot = self
+            .cast_slots
+            .iter()
+            .position(|spell| spell.as_ref() == Some(&current.spell_type));
+        Some(Hint {
+            slot,
+            orbs: optimal_sequences(&self.cast_buffer, &current.cast),
+        })
+    }
+
+    /// The spell the orbs in the buffer would invoke right now.
+    pub fn preview(&self) -> Option<&SpellDefinition> {
+        let mut buffer = self.input_buffer.buffer.clone();
+        buffer.sort_unstable();
+        self.spell_book
+            .spells()
+            .iter()
+            .find(|spell| spell.cast() == buffer)
+    }
+
+    // Why the run ended, `None` while it is still going
+    pub fn end(&self) -> Option<&RunEnd> {
+        self.end.as_ref()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.end.is_some()
+    }
+
+    /// Advances the run by `dt`: spawns and moves spells and checks if one reached the bottom.
+    pub fn step(&mut self, dt: Duration) -> Vec<Event> {
+        if self.is_over() {
+            return Vec::new();
+        }
+
+        self.last_spell_time += dt;
+        self.elapsed += dt;
+
+        // The spell of a time attack run waits in its slot until the time is up
+        if self.mode == GameMode::TimeAttack {
+            if self.time_left() == Some(Duration::ZERO) {
+                self.game_over(RunEnd::TimeUp);
+            }
+            return std::mem::take(&mut self.events);
+        }
+
+        self.speed = self.rules.initial_speed + self.rules.speed_ramp * self.elapsed.as_secs_f32();
+
+        if self.last_spell_time > self.rules.spawn_interval || self.spells.is_empty() {
+            self.last_spell_time = Duration::ZERO;
+
+            if self.mode == GameMode::Combos {
+                // Combos are fed in order, the next spell stays until it is on the screen
+            } else if self.mode.is_pro() {
+                // The next spell is cast right after the last one on the screen
+                let previous = self.spells.last().map(|spell| spell.spell_type.clone());
+                self.next_spell = Some(self.new_spell(previous.as_ref()));
+            } else {
+                let spell = self.new_spell(None);
+                self.spawn(spell);
+            }
+        }
+
+        for spell in self.spells.iter_mut() {
+            let speed = match self.mode {
+                GameMode::Pro | GameMode::GradedPro | GameMode::Combos => self.speed,
+                GameMode::Normal | GameMode::Daily | GameMode::Slots | GameMode::TimeAttack => {
+                    spell.speed
+                }
+            };
+            spell.position.y += speed * dt.as_secs_f32();
+        }
+
+        let fallen = self
+            .spells
+            .iter()
+            .find(|spell| spell.position.y > self.layout.height);
+        if let Some(spell) = fallen {
+            let end = RunEnd::SpellReachedBottom {
+                spell: spell.spell_type.clone(),
+            };
+            self.game_over(end);
+        }
+
+        std::mem::take(&mut self.events)
+    }
+
+    /// Handles a key press, `None` is a key that is not bound to any orb.
+    /// Those still count towards the key presses of a Pro mode spell.
+    pub fn press(&mut self, orb: Option<OrbType>) -> Vec<Event> {
+        if self.is_over() {
+            return Vec::new();
+        }
+
+        self.current_key_presses += 1;
+        self.total_key_presses += 1;
+
+        match orb {
+            Some(OrbType::FirstSlot) => self.cast_slot(0),
+            Some(OrbType::SecondSlot) => self.cast_slot(1),
+            orb => self.press_orb(orb),
+        }
+
+        std::mem::take(&mut self.events)
+    }
+
+    fn press_orb(&mut self, orb: Option<OrbType>) {
+        let cast = match orb.and_then(|orb| self.input_buffer.update_buffer(orb)) {
+            Some(cast) => cast,
+            None => {
+                if let (Some(_), Some(orb)) = (orb, self.input_buffer.buffer.last()) {
+                    self.events.push(Event::OrbPressed(*orb));
+                }
+                return;
+            }
+        };
+
+        match self.mode {
+            GameMode::Pro | GameMode::Combos => self.invoke_pro(cast),
+            GameMode::GradedPro => self.invoke_graded(cast),
+            GameMode::Normal | GameMode::Daily => self.invoke(cast),
+            GameMode::Slots => self.invoke_into_slot(cast),
+            GameMode::TimeAttack => self.invoke_time_attack(cast),
+        }
+    }
+
+    // Any spell on the screen can be cast
+    fn invoke(&mut self, mut cast: Vec<char>) {
+        cast.sort_unstable();
+
+        let index = self.spells.iter().position(|spell| cast == spell.cast);
+        if let Some(index) = index {
+            let spell = self.spells.remove(index);
+            self.load_slot(spell.spell_type.clone());
+            self.cast(&spell);
+            return;
+        }
+
+        let target = self.lowest_spell();
+        self.game_over(RunEnd::WrongSpell { cast, target });
+    }
+
+    // Slot mode only fills the slots, the spell is cast with its slot key afterwards.
+    // Orbs that don't make a spell invoke nothing
+    fn invoke_into_slot(&mut self, mut cast: Vec<char>) {
+        cast.sort_unstable();
+
+        let invoked = self
+            .spell_book
+            .spells()
+            .iter()
+            .find(|spell| spell.cast() == cast)
+            .map(|spell| spell.id.clone());
+        if let Some(spell) = invoked {
+            self.load_slot(spell);
+        }
+    }
+
+    fn load_slot(&mut self, spell: SpellType) {
+        load_slot(&mut self.slots, spell);
+    }
+
+    // An empty slot casts nothing, the press is wasted
+    fn cast_slot(&mut self, slot: usize) {
+        let slotted = match self.slots[slot].clone() {
+            Some(spell) => spell,
+            None => return,
+        };
+
+        match self.mode {
+            GameMode::Pro | GameMode::Combos => return self.cast_slot_pro(slotted),
+            GameMode::GradedPro => return self.cast_slot_graded(slotted),
+            GameMode::TimeAttack => return self.cast_slot_time_attack(slotted),
+            GameMode::Normal | GameMode::Daily | GameMode::Slots => {}
+        }
+
+        let index = self
+            .spells
+            .iter()
+            .position(|spell| spell.spell_type == slotted);
+        if let Some(index) = index {
+            let spell = self.spells.remove(index);
+            self.cast(&spell);
+            return;
+        }
+
+        let cast = self.recipe(&slotted);
+        let target = self.lowest_spell();
+        self.game_over(RunEnd::WrongSpell { cast, target });
+    }
+
+    // Only the spell in the slot can be cast, anything else costs time
+    fn invoke_time_attack(&mut self, mut cast: Vec<char>) {
+        cast.sort_unstable();
+
+        if cast != self.spells[0].cast {
+            self.miss();
+            return;
+        }
+
+        let current = self.spells[0].spell_type.clone();
+        self.load_slot(current);
+        self.cast_time_attack();
+    }
+
+    fn cast_slot_time_attack(&mut self, slotted: SpellType) {
+        if slotted != self.spells[0].spell_type {
+            self.miss();
+            return;
+        }
+        self.cast_time_attack();
+    }
+
+    // The next spell takes the place of the cast one right away
+    fn cast_time_attack(&mut self) {
+        let current = self.spells.remove(0);
+        self.cast(&current);
+        self.spawn_in_slot(Some(&current.spell_type));
+    }
+
+    // The presses of a wrong cast count towards the next spell, the streak is over
+    fn miss(&mut self) {
+        self.errors += 1;
+        self.penalty += WRONG_CAST_PENALTY;
+        self.streak = 0;
+        if self.time_left() == Some(Duration::ZERO) {
+            self.game_over(RunEnd::TimeUp);
+        }
+    }
+
+    // Time attack spells don't fall, they wait above the orbs
+    fn spawn_in_slot(&mut self, previous: Option<&SpellType>) {
+        let mut spell = self.new_spell(previous);
+        spell.position = Position {
+            x: (self.layout.width - self.layout.spell_size) / 2.0,
+            y: self.layout.height / 3.0,
+        };
+        self.spawn(spell);
+    }
+
+    // The lowest spell is the one the player was most likely going for
+    fn lowest_spell(&self) -> Option<SpellType> {
+        self.spells
+            .iter()
+            .max_by(|a, b| a.position.y.total_cmp(&b.position.y))
+            .map(|spell| spell.spell_type.clone())
+    }
+
+    fn recipe(&self, spell: &SpellType) -> Vec<char> {
+        self.spell_book
+            .spells()
+            .iter()
+            .find(|definition| &definition.id == spell)
+            .map(|definition| definition.cast())
+            .unwrap_or_default()
+    }
+
+    // The current spell has to be cast with as few key presses as possible
+    fn invoke_pro(&mut self, cast: Vec<char>) {
+        let current = self.spells[0].clone();
+
+        if self.current_key_presses != self.required_key_presses {
+            self.game_over(RunEnd::WrongKeyPressCount {
+                spell: current.spell_type.clone(),
+                pressed: self.current_key_presses,
+                required: self.required_key_presses,
+            });
+            return;
+        }
+
+        let mut sorted_cast = cast.clone();
+        sorted_cast.sort_unstable();
+
+        if sorted_cast != current.cast {
+            self.game_over(RunEnd::WrongSpell {
+                cast,
+                target: Some(current.spell_type),
+            });
+            return;
+        }
+
+        self.load_slot(current.spell_type.clone());
+        self.cast_pro(current);
+    }
+
+    // A spell that is already in a slot only needs its slot key. Invoking it
+    // again with the orbs is still fine, that takes the required presses
+    fn cast_slot_pro(&mut self, slotted: SpellType) {
+        let current = self.spells[0].clone();
+
+        if self.current_key_presses != 1 {
+            self.game_over(RunEnd::WrongKeyPressCount {
+                spell: current.spell_type.clone(),
+                pressed: self.current_key_presses,
+                required: 1,
+            });
+            return;
+        }
+
+        if slotted != current.spell_type {
+            self.game_over(RunEnd::WrongSpell {
+                cast: self.recipe(&slotted),
+                target: Some(current.spell_type),
+            });
+            return;
+        }
+
+        self.cast_pro(current);
+    }
+
+    // Graded Pro mode keeps going after a mistake, the wasted presses are
+    // taken from the budget and the run ends once it can't pay for them
+    fn invoke_graded(&mut self, cast: Vec<char>) {
+        let current = self.spells[0].clone();
+
+        let mut sorted_cast = cast.clone();
+        sorted_cast.sort_unstable();
+
+        if sorted_cast != current.cast {
+            let end = RunEnd::WrongSpell {
+                cast,
+                target: Some(current.spell_type),
+            };
+            self.spend_budget(WRONG_SPELL_PENALTY, end);
+            return;
+        }
+
+        self.load_slot(current.spell_type.clone());
+        self.cast_graded(current, self.required_key_presses);
+    }
+
+    fn cast_slot_graded(&mut self, slotted: SpellType) {
+        let current = self.spells[0].clone();
+
+        if slotted != current.spell_type {
+            let end = RunEnd::WrongSpell {
+                cast: self.recipe(&slotted),
+                target: Some(current.spell_type),
+            };
+            self.spend_budget(WRONG_SPELL_PENALTY, end);
+            return;
+        }
+
+        self.cast_graded(current, 1);
+    }
+
+    // Presses of wrong spells count towards the spell that is cast in the end
+    fn cast_graded(&mut self, current: Spell, required: usize) {
+        let grade = Grade {
+            required,
+            pressed: self.current_key_presses,
+        };
+        self.grades.push(grade);
+
+        let end = RunEnd::WrongKeyPressCount {
+            spell: current.spell_type.clone(),
+            pressed: grade.pressed,
+            required,
+        };
+        self.cast_pro(current);
+        self.spend_budget(grade.extra(), end);
+    }
+
+    fn spend_budget(&mut self, penalty: usize, end: RunEnd) {
+        match self.budget.checked_sub(penalty) {
+            Some(budget) => self.budget = budget,
+            None => {
+                self.budget = 0;
+                self.game_over(end);
+            }
+        }
+    }
+
+    fn cast_pro(&mut self, current: Spell) {
+        self.spells.remove(0);
+        self.cast(&current);
+
+        if let Some(next_spell) = self.next_spell.clone() {
+            self.spawn(next_spell);
+        }
+        if self.mode == GameMode::Combos {
+            self.next_spell = Some(self.new_spell(None));
+        }
+
+        self.required_key_presses =
+            buffer_transition_steps(&self.input_buffer.buffer, &self.spells[0].cast) + 1;
+        self.current_key_presses = 0;
+    }
+
+    fn new_spell(&mut self, previous: Option<&SpellType>) -> Spell {
+        let combo_spell = self.next_combo_spell();
+        let definition = combo_spell
+            .and_then(|spell| {
+                self.spell_book
+                    .spells()
+                    .iter()
+                    .find(|definition| definition.id == spell)
+            })
+            .unwrap_or_else(|| self.spawner.next(&self.spell_book, previous, &mut self.rng));
+        Spell::new(definition, self.speed, &self.layout, &mut self.rng)
+    }
+
+    // Combo drill only: the next spell of the last combo, a new one is picked once all of
+    // its spells are out. Without any combos the spawner picks the spells like in Pro mode
+    fn next_combo_spell(&mut self) -> Option<SpellType> {
+        if self.mode != GameMode::Combos || self.combo_book.combos().is_empty() {
+            return None;
+        }
+
+        if self.combo_spells.is_empty() {
+            let index = self.rng.gen_range(0..self.combo_book.combos().len());
+            self.combo_queue.push_back(index);
+            self.combo_spells
+                .extend(self.combo_book.combos()[index].spells.iter().cloned());
+        }
+        self.combo_spells.pop_front()
+    }
+
+    // The combo whose first spell is the current one, it starts from the last cast
+    fn start_combo(&self) -> Option<ComboProgress> {
+        let combo = self.combo_book.combos().get(*self.combo_queue.front()?)?;
+        let chain: Vec<(SpellType, Vec<char>)> = combo
+            .spells
+            .iter()
+            .map(|spell| (spell.clone(), self.recipe(spell)))
+            .collect();
+
+        Some(ComboProgress {
+            combo: combo.clone(),
+            cast: 0,
+            started: self.elapsed,
+            key_presses: 0,
+            fewest_key_presses: fewest_chain_key_presses(
+                &self.cast_buffer,
+                &self.cast_slots,
+                &chain,
+            ),
+        })
+    }
+
+    // A combo scores once its last spell is cast, the next one starts right away
+    fn advance_combo(&mut self, presses: usize) {
+        let combo = match &mut self.combo {
+            Some(combo) => combo,
+            None => return,
+        };
+        combo.cast += 1;
+        combo.key_presses += presses;
+        if combo.cast < combo.combo.spells.len() {
+            return;
+        }
+
+        self.score += 1;
+        self.combo_results.push(ComboResult {
+            name: combo.combo.name.clone(),
+            time: self.elapsed - combo.started,
+            key_presses: combo.key_presses,
+            fewest_key_presses: combo.fewest_key_presses,
+        });
+        self.combo_queue.pop_front();
+        self.combo = self.start_combo();
+    }
+
+    fn spawn(&mut self, mut spell: Spell) {
+        spell.id = self.spawned;
+        self.spawned += 1;
+
+        self.events.push(Event::SpellSpawned {
+            id: spell.id,
+            spell: spell.spell_type.clone(),
+            at: self.elapsed,
+        });
+        self.spells.push(spell);
+    }
+
+    fn cast(&mut self, spell: &Spell) {
+        // The combo drill scores whole combos instead
+        if self.combo.is_none() {
+            self.score += 1;
+        }
+
+        // Orbs left over from the last spell count, so the fewest presses depend on them
+        let invoke_presses = if self.cast_buffer.is_empty() {
+            FIRST_SPELL_KEY_PRESSES
+        } else {
+            buffer_transition_steps(&self.cast_buffer, &spell.cast) + 1
+        };
+        let slotted = self.cast_slots.contains(&Some(spell.spell_type.clone()));
+        let presses = self.current_key_presses;
+        let efficient = match self.mode {
+            // A spell that was still in a slot wastes every press but its slot key,
+            // any other one needs the slot key after its invoke
+            GameMode::Slots if slotted => presses == 1,
+            GameMode::Slots => presses == invoke_presses + 1,
+            GameMode::Normal
+            | GameMode::Pro
+            | GameMode::Daily
+            | GameMode::GradedPro
+            | GameMode::Combos
+            | GameMode::TimeAttack => presses == invoke_presses || (slotted && presses == 1),
+        };
+        if efficient {
+            self.streak += 1;
+        } else {
+            self.streak = 0;
+        }
+        self.cast_buffer = self.input_buffer.buffer.clone();
+        self.cast_slots = self.slots.clone();
+        self.current_key_presses = 0;
+
+        self.events.push(Event::SpellCast {
+            id: spell.id,
+            spell: spell.spell_type.clone(),
+            position: spell.position.clone(),
+            at: self.elapsed,
+        });
+        self.advance_combo(presses);
+    }
+
+    fn game_over(&mut self, end: RunEnd) {
+        self.events.push(Event::GameOver(end.clone()));
+        self.end = Some(end);
+    }
+}
+
+// A spell that is already in a slot moves to the first one, otherwise
+// the first slot moves over to the second
+fn load_slot(slots: &mut [Option<SpellType>; 2], spell: SpellType) {
+    if slots[0].as_ref() == Some(&spell) {
+        return;
+    }
+    if slots[1].as_ref() == Some(&spell) {
+        slots.swap(0, 1);
+    } else {
+        slots[1] = slots[0].take();
+        slots[0] = Some(spell);
+    }
+}
+
+/// Fewest key presses that cast `chain`, every spell with its recipe, one after another
+/// with Pro mode rules, starting from `buffer` and `slots`. Each spell is cast from a slot
+/// or invoked with the fewest orbs, but which of those orders is taken can save presses later.
+pub fn fewest_chain_key_presses(
+    buffer: &[char],
+    slots: &[Option<SpellType>; 2],
+    chain: &[(SpellType, Vec<char>)],
+) -> usize {
+    let mut states = HashMap::from([((buffer.to_vec(), slots.clone()), 0)]);
+
+    for (spell, recipe) in chain {
+        let mut next_states = HashMap::new();
+        for ((buffer, slots), presses) in states {
+            let mut reach = |state, presses: usize| {
+                let best = next_states.entry(state).or_insert(presses);
+                *best = presses.min(*best);
+            };
+
+            if slots.contains(&Some(spell.clone())) {
+                reach((buffer.clone(), slots.clone()), presses + 1);
+            }
+            for sequence in optimal_sequences(&buffer, recipe) {
+                let mut new_buffer = buffer.clone();
+                for orb in &sequence {
+                    if new_buffer.len() == 3 {
+                        new_buffer.remove(0);
+                    }
+                    new_buffer.push(*orb);
+                }
+                let mut new_slots = slots.clone();
+                load_slot(&mut new_slots, spell.clone());
+                reach((new_buffer, new_slots), presses + sequence.len() + 1);
+            }
+        }
+        states = next_states;
+    }
+
+    states.into_values().min().unwrap_or(0)
+}
+
+/// Fewest orb presses that turn the `left` buffer into one that invokes `right`.
+pub fn buffer_transition_steps(left: &[char], right: &[char]) -> usize {
+    optimal_sequences(left, right).first().map_or(0, Vec::len)
+}
+
+/// Every shortest orb sequence that turns the `left` buffer into one that invokes `right`.
+/// The buffer may hold fewer than three orbs, e.g. none before the first invoke.
+pub fn optimal_sequences(left: &[char], right: &[char]) -> Vec<Vec<char>> {
+    let sorted = |buffer: &[char]| {
+        let mut tmp = buffer.to_vec();
+        tmp.sort_unstable();
+        tmp
+    };
+    let right_sorted = sorted(right);
+
+    // Buffers reached with the same number of presses, each with the sequence that got there.
+    // A buffer reached in several ways keeps all of them, so ties are not lost
+    let mut layer = vec![(left.to_vec(), Vec::new())];
+    let mut visited = HashSet::new();
+    visited.insert(left.to_vec());
+
+    while !layer.is_empty() {
+        let done: Vec<Vec<char>> = layer
+            .iter()
+            .filter(|(buffer, _)| sorted(buffer) == right_sorted)
+            .map(|(_, sequence)| sequence.clone())
+            .collect();
+        if !done.is_empty() {
+            return done;
+        }
+
+        let mut next_layer = Vec::new();
+        let mut reached = HashSet::new();
+        for (buffer, sequence) in &layer {
+            for &key in ['Q', 'W', 'E'].iter() {
+                // A full buffer drops its oldest orb
+                let mut new_buffer = buffer.clone();
+                if new_buffer.len() == 3 {
+                    new_buffer.remove(0);
+                }
+                new_buffer.push(key);
+
+                if visited.contains(&new_buffer) {
+                    continue;
+                }
+                let mut new_sequence = sequence.clone();
+                new_sequence.push(key);
+                reached.insert(new_buffer.clone());
+                next_layer.push((new_buffer, new_sequence));
+            }
+        }
+        visited.extend(reached);
+        layer = next_layer;
+    }
+
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: Duration = Duration::from_millis(16);
+
+    fn orb(key: char) -> OrbType {
+        match key {
+            'Q' => OrbType::Quas,
+            'W' => OrbType::Wex,
+            _ => OrbType::Exort,
+        }
+    }
+
+    // Presses the orbs of `cast` followed by invoke
+    fn invoke(simulation: &mut Simulation, cast: &[char]) -> Vec<Event> {
+        let mut events = Vec::new();
+        for key in cast {
+            events.extend(simulation.press(Some(orb(*key))));
+        }
+        events.extend(simulation.press(Some(OrbType::Invoke)));
+        events
+    }
+
+    fn simulation(mode: GameMode) -> Simulation {
+        let spell_book = SpellBook::default();
+        Simulation::new(
+            mode,
+            7,
+            Spawner::uniform(),
+            spell_book,
+            ComboBook::default(),
+            1024.0,
+            1024.0,
+        )
+    }
+
+    fn normal() -> Simulation {
+        simulation(GameMode::Normal)
+    }
+
+    #[test]
+    fn test_casting_a_spell_scores() {
+        let mut simulation = normal();
+        let events = simulation.step(DT);
+        assert!(matches!(events[..], [Event::SpellSpawned { id: 0, .. }]));
+
+        let spell = simulation.spells()[0].clone();
+        let events = invoke(&mut simulation, &spell.cast);
+
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[3], Event::SpellCast { id: 0, .. }));
+        assert_eq!(simulation.score(), 1);
+        assert!(simulation.spells().is_empty());
+        assert!(!simulation.is_over());
+    }
+
+    #[test]
+    fn test_streak_and_preview() {
+        let mut simulation = normal();
+        simulation.step(DT);
+        assert_eq!(simulation.preview(), None);
+
+        let spell = simulation.spells()[0].clone();
+        for key in &spell.cast {
+            simulation.press(Some(orb(*key)));
+        }
+        assert_eq!(simulation.preview().unwrap().id, spell.spell_type);
+        simulation.press(Some(OrbType::Invoke));
+        assert_eq!(simulation.streak(), 1);
+
+        while simulation.spells().is_empty() {
+            simulation.step(DT);
+        }
+
+        // A key that isn't bound to an orb is a wasted press
+        simulation.press(None);
+        let spell = simulation.spells()[0].clone();
+        invoke(&mut simulation, &spell.cast);
+        assert_eq!(simulation.score(), 2);
+        assert_eq!(simulation.streak(), 0);
+    }
+
+    #[test]
+    fn test_wrong_spell_ends_the_run() {
+        let mut simulation = normal();
+        simulation.step(DT);
+
+        let spell = simulation.spells()[0].spell_type.clone();
+        let spell_book = SpellBook::default();
+        let wrong = spell_book
+            .spells()
+            .iter()
+            .find(|other| other.id != spell)
+            .unwrap();
+        let events = invoke(&mut simulation, &wrong.cast());
+
+        let end = RunEnd::WrongSpell {
+            cast: wrong.cast(),
+            target: Some(spell),
+        };
+        assert_eq!(events.last(), Some(&Event::GameOver(end.clone())));
+        assert_eq!(simulation.end(), Some(&end));
+
+        // Nothing happens once the run is over
+        assert!(simulation.step(DT).is_empty());
+        assert!(simulation.press(Some(OrbType::Invoke)).is_empty());
+    }
+
+    #[test]
+    fn test_spell_reaching_the_bottom_ends_the_run() {
+        let mut simulation = normal();
+        let mut events = Vec::new();
+        while !simulation.is_over() {
+            events.extend(simulation.step(DT));
+        }
+
+        assert!(matches!(
+            events.last(),
+            Some(Event::GameOver(RunEnd::SpellReachedBottom { .. }))
+        ));
+        // Later spells are faster, so the one that got through is not necessarily the first
+        let fallen = simulation
+            .spells()
+            .iter()
+            .find(|spell| spell.position.y > 1024.0)
+            .unwrap();
+        assert_eq!(
+            simulation.end(),
+            Some(&RunEnd::SpellReachedBottom {
+                spell: fallen.spell_type.clone()
+            })
+        );
+    }
+
+    #[test]
+    fn test_pro_mode_counts_key_presses() {
+        let mut simulation = simulation(GameMode::Pro);
+        let current = simulation.spells()[0].clone();
+
+        // Keys that are not bound to an orb count as well
+        simulation.press(None);
+        invoke(&mut simulation, &current.cast);
+
+        assert_eq!(
+            simulation.end(),
+            Some(&RunEnd::WrongKeyPressCount {
+                spell: current.spell_type.clone(),
+                pressed: 5,
+                required: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_pro_mode_shows_the_next_spell() {
+        let mut simulation = simulation(GameMode::Pro);
+        let current = simulation.spells()[0].clone();
+
+        let events = invoke(&mut simulation, &current.cast);
+
+        // The initial spell is reported along with the first events
+        assert!(matches!(events[0], Event::SpellSpawned { id: 0, .. }));
+        assert!(matches!(
+            events[events.len() - 2..],
+            [
+                Event::SpellCast { id: 0, .. },
+                Event::SpellSpawned { id: 1, .. }
+            ]
+        ));
+        assert_eq!(simulation.spells().len(), 1);
+        assert_eq!(simulation.score(), 1);
+    }
+
+    #[test]
+    fn test_graded_pro_mode_spends_the_budget() {
+        let mut simulation = simulation(GameMode::GradedPro);
+        assert_eq!(simulation.budget(), Some(PENALTY_BUDGET));
+
+        let current = simulation.spells()[0].clone();
+        simulation.press(None);
+        simulation.press(None);
+        invoke(&mut simulation, &current.cast);
+        assert!(!simulation.is_over());
+        assert_eq!(simulation.score(), 1);
+        assert_eq!(simulation.budget(), Some(PENALTY_BUDGET - 2));
+        assert_eq!(simulation.grades().summary(), "Efficiency 67%: 1 at +2");
+
+        // A wrong spell costs the penalty, the current spell stays
+        let current = simulation.spells()[0].clone();
+        let spell_book = SpellBook::default();
+        let wrong = spell_book
+            .spells()
+            .iter()
+            .find(|other| other.id != current.spell_type)
+            .unwrap();
+        invoke(&mut simulation, &wrong.cast());
+        assert!(!simulation.is_over());
+        assert_eq!(simulation.spells()[0].id, current.id);
+        assert_eq!(
+            simulation.budget(),
+            Some(PENALTY_BUDGET - 2 - WRONG_SPELL_PENALTY)
+        );
+
+        // More wasted presses than are left end the run
+        for _ in 0..PENALTY_BUDGET {
+            simulation.press(None);
+        }
+        invoke(&mut simulation, &current.cast);
+        assert_eq!(simulation.budget(), Some(0));
+        assert!(matches!(
+            simulation.end(),
+            Some(RunEnd::WrongKeyPressCount { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invoked_spells_fill_the_slots() {
+        let mut simulation = simulation(GameMode::Slots);
+        let spell_book = SpellBook::default();
+        let first = &spell_book.spells()[0];
+        let second = &spell_book.spells()[1];
+
+        // Nothing to cast yet, the press is wasted
+        assert!(simulation.press(Some(OrbType::FirstSlot)).is_empty());
+
+        invoke(&mut simulation, &first.cast());
+        assert_eq!(simulation.slots(), &[Some(first.id.clone()), None]);
+        invoke(&mut simulation, &second.cast());
+        assert_eq!(
+            simulation.slots(),
+            &[Some(second.id.clone()), Some(first.id.clone())]
+        );
+
+        // A spell that is already in a slot only moves to the first one
+        invoke(&mut simulation, &first.cast());
+        assert_eq!(
+            simulation.slots(),
+            &[Some(first.id.clone()), Some(second.id.clone())]
+        );
+
+        // Invoking never casts in slot mode
+        assert_eq!(simulation.score(), 0);
+        assert!(!simulation.is_over());
+    }
+
+    #[test]
+    fn test_slot_mode_casts_from_slots() {
+        let mut simulation = simulation(GameMode::Slots);
+        simulation.step(DT);
+
+        let spell = simulation.spells()[0].clone();
+        let events = invoke(&mut simulation, &spell.cast);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, Event::SpellCast { .. })));
+
+        let events = simulation.press(Some(OrbType::FirstSlot));
+        assert!(matches!(events[..], [Event::SpellCast { id: 0, .. }]));
+        assert_eq!(simulation.streak(), 1);
+
+        while simulation.spells().is_empty() {
+            simulation.step(DT);
+        }
+
+        // As if the next spell had been invoked before, invoking it again is a waste
+        let spell = simulation.spells()[0].clone();
+        simulation.slots = [Some(spell.spell_type.clone()), None];
+        simulation.cast_slots = simulation.slots.clone();
+        invoke(&mut simulation, &spell.cast);
+        simulation.press(Some(OrbType::FirstSlot));
+        assert_eq!(simulation.score(), 2);
+        assert_eq!(simulation.streak(), 0);
+    }
+
+    #[test]
+    fn test_slot_cast_needs_the_spell_on_screen() {
+        let mut simulation = normal();
+        simulation.step(DT);
+
+        let spell = simulation.spells()[0].clone();
+        invoke(&mut simulation, &spell.cast);
+        assert_eq!(simulation.slots()[0], Some(spell.spell_type));
+
+        let events = simulation.press(Some(OrbType::FirstSlot));
+        let end = RunEnd::WrongSpell {
+            cast: spell.cast,
+            target: None,
+        };
+        assert_eq!(events, [Event::GameOver(end)]);
+    }
+
+    #[test]
+    fn test_same_seed_and_inputs_give_same_events() {
+        let play = || {
+            let mut simulation = normal();
+            let mut events = Vec::new();
+            for tick in 0..600 {
+                if tick % 50 == 0 {
+                    if let Some(spell) = simulation.spells().first().cloned() {
+                        events.extend(invoke(&mut simulation, &spell.cast));
+                    }
+                }
+                events.extend(simulation.step(DT));
+            }
+            events
+        };
+
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn test_optimal_sequences() {
+        // Ties are all kept, in the order Q, W, E
+        assert_eq!(
+            optimal_sequences(&['W', 'W', 'W'], &['Q', 'W', 'E']),
+            [vec!['Q', 'E'], vec!['E', 'Q']]
+        );
+        assert_eq!(
+            optimal_sequences(&['Q', 'W', 'W'], &['W', 'W', 'E']),
+            [vec!['E']]
+        );
+        assert_eq!(
+            optimal_sequences(&['Q', 'W', 'E'], &['E', 'W', 'Q']),
+            [Vec::<char>::new()]
+        );
+        assert_eq!(optimal_sequences(&[], &['Q', 'W', 'E']).len(), 6);
+    }
+
+    #[test]
+    fn test_pro_mode_hint() {
+        assert_eq!(normal().hint(), None);
+
+        let mut simulation = simulation(GameMode::Pro);
+        let current = simulation.spells()[0].clone();
+        let hint = simulation.hint().unwrap();
+        assert_eq!(hint.slot, None);
+        assert!(hint.orbs.contains(&current.cast));
+
+        invoke(&mut simulation, &current.cast);
+        let next = simulation.spells()[0].clone();
+        let hint = simulation.hint().unwrap();
+        assert_eq!(
+            hint.orbs[0].len(),
+            buffer_transition_steps(simulation.buffer(), &next.cast)
+        );
+    }
+
+    #[test]
+    fn test_combo_drill_scores_whole_combos() {
+        let combo_book = ComboBook::parse(
+            "[[combos]]\nname = \"Snap\"\nspells = [\"ColdSnap\", \"ForgeSpirit\", \"Alacrity\"]\n",
+            &SpellBook::default(),
+        )
+        .unwrap();
+        let mut simulation = Simulation::new(
+            GameMode::Combos,
+            7,
+            Spawner::uniform(),
+            SpellBook::default(),
+            combo_book,
+            1024.0,
+            1024.0,
+        );
+
+        // QQQ, then EE and WW from what is left in the buffer
+        let combo = simulation.combo().unwrap().clone();
+        assert_eq!(combo.combo.name, "Snap");
+        assert_eq!(combo.fewest_key_presses, 10);
+
+        let casts: [&[char]; 3] = [&['Q', 'Q', 'Q'], &['E', 'E'], &['W', 'W']];
+        for (index, cast) in casts.into_iter().enumerate() {
+            assert_eq!(simulation.score(), 0);
+            assert_eq!(simulation.spells()[0].spell_type, combo.combo.spells[index]);
+            for _ in 0..10 {
+                simulation.step(DT);
+            }
+            invoke(&mut simulation, cast);
+            assert!(!simulation.is_over());
+        }
+
+        assert_eq!(simulation.score(), 1);
+        let result = &simulation.combo_results()[0];
+        assert_eq!(result.time, DT * 30);
+        assert_eq!(result.key_presses, 10);
+        assert_eq!(simulation.combo().unwrap().cast, 0);
+    }
+
+    #[test]
+    fn test_time_attack_runs_out_of_time() {
+        let mut simulation =
+            simulation(GameMode::TimeAttack).with_time_limit(Duration::from_secs(3));
+        assert_eq!(simulation.time_left(), Some(Duration::from_secs(3)));
+
+        // A cast spell is replaced right away, spells don't fall
+        let current = simulation.spells()[0].clone();
+        invoke(&mut simulation, &current.cast);
+        assert_eq!(simulation.score(), 1);
+        assert_eq!(simulation.spells().len(), 1);
+        assert_eq!(simulation.spells()[0].position, current.position);
+
+        // A wrong spell costs time, the current spell stays
+        let current = simulation.spells()[0].clone();
+        let spell_book = SpellBook::default();
+        let wrong = spell_book
+            .spells()
+            .iter()
+            .find(|other| other.id != current.spell_type)
+            .unwrap();
+        invoke(&mut simulation, &wrong.cast());
+        assert!(!simulation.is_over());
+        assert_eq!(simulation.errors(), 1);
+        assert_eq!(simulation.spells()[0].id, current.id);
+        assert_eq!(simulation.time_left(), Some(Duration::from_secs(1)));
+
+        while !simulation.is_over() {
+            simulation.step(DT);
+        }
+        assert_eq!(simulation.end(), Some(&RunEnd::TimeUp));
+        assert_eq!(
+            simulation.time_attack_result(),
+            TimeAttackResult {
+                time_limit: Duration::from_secs(3),
+                spells: 1,
+                key_presses: current.cast.len() + wrong.cast().len() + 2,
+                errors: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_time_attack_result() {
+        let result = TimeAttackResult {
+            time_limit: Duration::from_secs(30),
+            spells: 15,
+            key_presses: 60,
+            errors: 2,
+        };
+        assert_eq!(result.spells_per_minute(), 30.0);
+        assert_eq!(result.keys_per_spell(), Some(4.0));
+        assert_eq!(
+            result.summary(),
+            "15 spells in 30s, 4.0 keys per spell, 2 errors"
+        );
+
+        let result = TimeAttackResult {
+            spells: 0,
+            errors: 1,
+            ..result
+        };
+        assert_eq!(result.keys_per_spell(), None);
+        assert_eq!(result.summary(), "0 spells in 30s, 1 error");
+    }
+
+    #[test]
+    fn test_fewest_chain_key_presses() {
+        let chain = |spells: &[&str]| -> Vec<(SpellType, Vec<char>)> {
+            let spell_book = SpellBook::default();
+            spells
+                .iter()
+                .map(|id| {
+                    let definition = spell_book
+                        .spells()
+                        .iter()
+                        .find(|spell| spell.id == SpellType::new(id))
+                        .unwrap();
+                    (definition.id.clone(), definition.cast())
+                })
+                .collect()
+        };
+
+        assert_eq!(fewest_chain_key_presses(&[], &[None, None], &[]), 0);
+
+        // Cold Snap is still in a slot the second time
+        let snap = chain(&["ColdSnap", "Tornado", "ColdSnap"]);
+        assert_eq!(fewest_chain_key_presses(&[], &[None, None], &snap), 8);
+
+        let slots = [Some(SpellType::new("Tornado")), None];
+        let tornado = chain(&["Tornado", "Emp"]);
+        assert_eq!(
+            fewest_chain_key_presses(&['Q', 'W', 'W'], &slots, &tornado),
+            3
+        );
+    }
+
+    #[test]
+    fn test_buffer_transition_steps() {
+        let left = vec!['Q', 'W', 'E'];
+        let right = vec!['W', 'W', 'W'];
+
+        let steps = buffer_transition_steps(&left, &right);
+        assert_eq!(steps, 3);
+
+        let left = vec!['W', 'W', 'W'];
+        let right = vec!['Q', 'W', 'W'];
+
+        let steps = buffer_transition_steps(&left, &right);
+        assert_eq!(steps, 1);
+
+        let left = vec!['Q', 'W', 'W'];
+        let right = vec!['Q', 'W', 'E'];
+
+        let steps = buffer_transition_steps(&left, &right);
+        assert_eq!(steps, 2);
+
+        let left = vec!['Q', 'Q', 'W'];
+        let right = vec!['Q', 'Q', 'E'];
+
+        let steps = buffer_transition_steps(&left, &right);
+        assert_eq!(steps, 3);
+
+        let left = vec!['W', 'Q', 'Q'];
+        let right = vec!['Q', 'Q', 'E'];
+
+        let steps = buffer_transition_steps(&left, &right);
+        assert_eq!(steps, 1);
+
+        let left = vec!['Q', 'W', 'E'];
+        let right = vec!['E', 'E', 'E'];
+
+        let steps = buffer_transition_steps(&left, &right);
+        assert_eq!(steps, 2);
+
+        let left = vec!['Q', 'W', 'E'];
+        let right = vec!['Q', 'W', 'W'];
+
+        let steps = buffer_transition_steps(&left, &right);
+        assert_eq!(steps, 3);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::run_history::RunRecord;
+use crate::spell_book::{SpellBook, SpellDefinition};
+use crate::spells::SpellType;
+use crate::statistics::Statistics;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Upper bound on how much more often a weak spell shows up than an average one
+const MAX_SPELL_WEIGHT: f32 = 4.0;
+
+// Seeds stay below i64::MAX so they can be stored in TOML
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen_range(0..i64::MAX as u64)
+}
+
+// Everyone gets the same seed on the same day, e.g. 20240131
+pub fn daily_seed() -> u64 {
+    seed_of_day(chrono::Local::now().date_naive())
+}
+
+// The seed `daily_seed` gives on `date`
+fn seed_of_day(date: chrono::NaiveDate) -> u64 {
+    date.format("%Y%m%d")
+        .to_string()
+        .parse()
+        .expect("Date is always a number")
+}
+
+/// Picks the next spell, either uniformly or biased towards the player's weak spots.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Spawner {
+    // Weight of every spell in `SpellBook` order. The first row is used when there is
+    // no previous spell, the others when the previous spell is the spell of `row - 1`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weights: Option<Vec<Vec<f32>>>,
+}
+
+impl Spawner {
+    pub fn new(adaptive: bool, spell_book: &SpellBook) -> Self {
+        if adaptive {
+            Self::adaptive(&Statistics::from_runs(&RunRecord::load_all()), spell_book)
+        } else {
+            Self::uniform()
+        }
+    }
+
+    pub fn uniform() -> Self {
+        Self { weights: None }
+    }
+
+    pub fn adaptive(statistics: &Statistics, spell_book: &SpellBook) -> Self {
+        let spells = spell_book.spells();
+        let previous_spells = std::iter::once(None).chain(spells.iter().map(Some));
+        let weights = previous_spells
+            .map(|previous| {
+                spells
+                    .iter()
+                    .map(|spell| {
+                        Self::adaptive_weight(statistics, &spell.id, previous.map(|p| &p.id))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            weights: Some(weights),
+        }
+    }
+
+    /// `previous` is the spell the player has to cast right before this one (Pro mode),
+    /// slow or error prone transitions from it are picked more often.
+    pub fn next<'a>(
+        &self,
+        spell_book: &'a SpellBook,
+        previous: Option<&SpellType>,
+        rng: &mut impl Rng,
+    ) -> &'a SpellDefinition {
+        let spells = spell_book.spells();
+        let previous = previous.and_then(|previous| Self::index(spell_book, previous));
+        let weights = (0..spells.len()).map(|spell| self.weight(spell, previous));
+        let distribution = WeightedIndex::new(weights).expect("Weights are always positive");
+
+        &spells[distribution.sample(rng)]
+    }
+
+    fn weight(&self, spell: usize, previous: Option<usize>) -> f32 {
+        let weights = match &self.weights {
+            Some(weights) => weights,
+            None => return 1.0,
+        };
+
+        let row = previous.map_or(0, |previous| previous + 1);
+        weights
+            .get(row)
+            .and_then(|row| row.get(spell))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    fn index(spell_book: &SpellBook, spell: &SpellType) -> Option<usize> {
+        spell_book
+            .spells()
+            .iter()
+            .position(|other| other.id == *spell)
+    }
+
+    fn adaptive_weight(
+        statistics: &Statistics,
+        spell: &SpellType,
+        previous: Option<&SpellType>,
+    ) -> f32 {
+        let stats = statistics.spell(spell);
+
+        // Spells that take longer than usual to cast
+        let slowness = match (stats.average_cast_time(), statistics.average_cast_time()) {
+            (Some(spell_time), Some(average_time)) if average_time > 0.0 => {
+                spell_time / average_time
+            }
+            _ => 1.0,
+        };
+
+        // Spells that end runs
+        let misses = 1.0 + 2.0 * stats.miss_rate().unwrap_or(0.0);
+
+        // Buffer transitions that go wrong in Pro mode
+        let transition = previous
+            .and_then(|previous| statistics.transition(previous, spell).failure_rate())
+            .map_or(1.0, |failure_rate| 1.0 + 2.0 * failure_rate);
+
+        (slowness * misses * transition).clamp(1.0 / MAX_SPELL_WEIGHT, MAX_SPELL_WEIGHT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highscores::GameMode;
+    use crate::run_history::{RunEnd, SpellRecord, RUN_FORMAT_VERSION};
+
+    fn spell(id: &str) -> SpellType {
+        SpellType::new(id)
+    }
+
+    // Weight of `spell` after `previous` in the default spell book
+    fn weight(spawner: &Spawner, spell: &str, previous: Option<&str>) -> f32 {
+        let spell_book = SpellBook::default();
+        let index = |id| Spawner::index(&spell_book, &SpellType::new(id)).unwrap();
+        spawner.weight(index(spell), previous.map(index))
+    }
+
+    fn pro_run(spells: &[(SpellType, Option<f32>)], end: RunEnd) -> RunRecord {
+        RunRecord {
+            version: RUN_FORMAT_VERSION,
+            seed: None,
+            mode: GameMode::Pro,
+            started_at: String::from("2024-01-01T00:00:00.000"),
+            score: 0,
+            duration: 10.0,
+            end,
+            spells: spells
+                .iter()
+                .map(|(spell, cast_at)| SpellRecord {
+                    spell: spell.clone(),
+                    spawned_at: 0.0,
+                    cast_at: *cast_at,
+                    keys: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_spells() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let spawner = Spawner::uniform();
+        let spell_book = SpellBook::default();
+        let mut first = StdRng::seed_from_u64(20240307);
+        let mut second = StdRng::seed_from_u64(20240307);
+
+        for _ in 0..50 {
+            assert_eq!(
+                spawner.next(&spell_book, None, &mut first),
+                spawner.next(&spell_book, None, &mut second)
+            );
+        }
+    }
+
+    #[test]
+    fn test_daily_seed_is_the_date() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+        assert_eq!(seed_of_day(date), 20240307);
+    }
+
+    #[test]
+    fn test_uniform_weights() {
+        let spawner = Spawner::uniform();
+        for spell in SpellBook::default().spells() {
+            assert_eq!(weight(&spawner, &spell.id.to_string(), None), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_weights_favour_weak_spots() {
+        let runs = vec![
+            pro_run(
+                &[
+                    (spell("ColdSnap"), Some(1.0)),
+                    (spell("ForgeSpirit"), Some(3.0)),
+                    (spell("ChaosMeteor"), None),
+                ],
+                RunEnd::WrongKeyPressCount {
+                    spell: spell("ChaosMeteor"),
+                    pressed: 4,
+                    required: 2,
+                },
+            ),
+            pro_run(
+                &[
+                    (spell("ColdSnap"), Some(1.0)),
+                    (spell("ChaosMeteor"), Some(1.0)),
+                ],
+                RunEnd::Quit,
+            ),
+        ];
+        let spawner = Spawner::adaptive(&Statistics::from_runs(&runs), &SpellBook::default());
+
+        // Slow to cast
+        assert!(weight(&spawner, "ForgeSpirit", None) > 1.0);
+        assert!(weight(&spawner, "ColdSnap", None) < 1.0);
+        // No data at all
+        assert_eq!(weight(&spawner, "Emp", None), 1.0);
+
+        // Forge Spirit -> Chaos Meteor went wrong, Cold Snap -> Chaos Meteor did not
+        assert!(
+            weight(&spawner, "ChaosMeteor", Some("ForgeSpirit"))
+                > weight(&spawner, "ChaosMeteor", Some("ColdSnap"))
+        );
+    }
+}
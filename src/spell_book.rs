@@ -0,0 +1,238 @@
+use crate::spells::SpellType;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::{fs, io::Read};
+
+use serde::{Deserialize, Serialize};
+
+const EMBEDDED_SPELLS: &str = include_str!("../resources/spells.toml");
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SpellDefinition {
+    pub id: SpellType,
+    pub name: String,
+    // Three of Q, W and E in any order
+    pub recipe: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl SpellDefinition {
+    // Orbs are sorted so a sorted buffer can be compared directly
+    pub fn cast(&self) -> Vec<char> {
+        let mut cast: Vec<char> = self.recipe.chars().collect();
+        cast.sort_unstable();
+        cast
+    }
+}
+
+// A spell as written in spells.toml, the id may be left out
+#[derive(Debug, Deserialize)]
+struct SpellEntry {
+    id: Option<String>,
+    name: String,
+    recipe: String,
+    icon: Option<PathBuf>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpellFile {
+    spells: Vec<SpellEntry>,
+}
+
+/// Every spell that can show up in a run.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SpellBook {
+    spells: Vec<SpellDefinition>,
+}
+
+impl Default for SpellBook {
+    fn default() -> Self {
+        Self::parse(EMBEDDED_SPELLS).expect("Embedded spells are valid")
+    }
+}
+
+impl SpellBook {
+    /// Reads spells.toml from `dir`, the directory of the settings file in use. Without one,
+    /// or if it can't be used, the spells that come with the game are used and what was
+    /// wrong is returned.
+    pub fn load(dir: &Path) -> (Self, Vec<String>) {
+        Self::load_from(&dir.join("spells.toml"))
+    }
+
+    fn load_from(path: &Path) -> (Self, Vec<String>) {
+        if !path.exists() {
+            return (Self::default(), Vec::new());
+        }
+
+        let mut contents = String::new();
+        let read = fs::File::open(path).and_then(|mut file| file.read_to_string(&mut contents));
+
+        let spell_book = read
+            .map_err(|e| e.into())
+            .and_then(|_| Self::parse(&contents));
+        match spell_book {
+            Ok(spell_book) => (spell_book.with_icons_relative_to(path.parent()), Vec::new()),
+            Err(e) => (
+                Self::default(),
+                vec![format!(
+                    "Using the default spells, {} is invalid: {}",
+                    path.display(),
+                    e
+                )],
+            ),
+        }
+    }
+
+    /// Parses and validates a spell file: every recipe has exactly three orbs,
+    /// no two spells share a recipe and ids are unique.
+    pub fn parse(contents: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file: SpellFile = toml::from_str(contents)?;
+        if file.spells.is_empty() {
+            return Err("There has to be at least one spell".into());
+        }
+
+        let mut ids = HashSet::new();
+        let mut recipes = HashSet::new();
+        let mut spells = Vec::with_capacity(file.spells.len());
+
+        for entry in file.spells {
+            let recipe = entry.recipe.to_uppercase();
+            let id = SpellType::new(entry.id.as_deref().unwrap_or(&entry.name));
+
+            if recipe.chars().count() != 3 {
+                return Err(format!(
+                    "{} needs exactly three orbs, got \"{}\"",
+                    entry.name, entry.recipe
+                )
+                .into());
+            }
+            if let Some(orb) = recipe.chars().find(|orb| !['Q', 'W', 'E'].contains(orb)) {
+                return Err(format!("{} has an unknown orb {}", entry.name, orb).into());
+            }
+
+            let spell = SpellDefinition {
+                id,
+                name: entry.name,
+                recipe,
+                icon: entry.icon,
+                description: entry.description,
+            };
+
+            if !ids.insert(spell.id.clone()) {
+                return Err(format!("{} is defined twice", spell.id).into());
+            }
+            if !recipes.insert(spell.cast()) {
+                return Err(format!(
+                    "{} has the same recipe as another spell: {}",
+                    spell.name, spell.recipe
+                )
+                .into());
+            }
+
+            spells.push(spell);
+        }
+
+        Ok(Self { spells })
+    }
+
+    pub fn spells(&self) -> &[SpellDefinition] {
+        &self.spells
+    }
+
+    fn with_icons_relative_to(mut self, dir: Option<&Path>) -> Self {
+        if let Some(dir) = dir {
+            for spell in &mut self.spells {
+                spell.icon = spell.icon.take().map(|icon| dir.join(icon));
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get<'a>(spell_book: &'a SpellBook, id: &str) -> &'a SpellDefinition {
+        spell_book
+            .spells()
+            .iter()
+            .find(|spell| spell.id == SpellType::new(id))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_embedded_spells() {
+        let spell_book = SpellBook::default();
+        assert_eq!(spell_book.spells().len(), 10);
+
+        let cold_snap = get(&spell_book, "ColdSnap");
+        assert_eq!(cold_snap.name, "Cold Snap");
+        assert_eq!(cold_snap.cast(), vec!['Q', 'Q', 'Q']);
+
+        let alacrity = get(&spell_book, "Alacrity");
+        assert_eq!(alacrity.cast(), vec!['E', 'W', 'W']);
+    }
+
+    #[test]
+    fn test_custom_spells() {
+        let contents = r#"
+            [[spells]]
+            name = "Fireball"
+            recipe = "eeq"
+            icon = "fireball.png"
+
+            [[spells]]
+            id = "Frost"
+            name = "Frost Nova"
+            recipe = "QQQ"
+            description = "Cold"
+        "#;
+
+        let spell_book = SpellBook::parse(contents)
+            .unwrap()
+            .with_icons_relative_to(Some(Path::new("/config")));
+
+        let fireball = get(&spell_book, "Fireball");
+        assert_eq!(fireball.recipe, "EEQ");
+        assert_eq!(fireball.icon, Some(PathBuf::from("/config/fireball.png")));
+        assert_eq!(get(&spell_book, "Frost").name, "Frost Nova");
+    }
+
+    #[test]
+    fn test_invalid_spells() {
+        let spell = |name: &str, recipe: &str| {
+            format!("[[spells]]\nname = \"{}\"\nrecipe = \"{}\"\n", name, recipe)
+        };
+
+        assert!(SpellBook::parse("spells = []").is_err());
+        assert!(SpellBook::parse(&spell("Short", "QW")).is_err());
+        assert!(SpellBook::parse(&spell("Long", "QWEQ")).is_err());
+        assert!(SpellBook::parse(&spell("Unknown", "QWR")).is_err());
+
+        let same_recipe = spell("First", "QWE") + &spell("Second", "EWQ");
+        assert!(SpellBook::parse(&same_recipe).is_err());
+
+        let same_name = spell("Twice", "QQQ") + &spell("Twice", "WWW");
+        assert!(SpellBook::parse(&same_name).is_err());
+    }
+
+    #[test]
+    fn test_invalid_spell_file_is_reported() {
+        let path = std::env::temp_dir().join(format!("invoke-spells-{}.toml", std::process::id()));
+        fs::write(&path, "spells = []").unwrap();
+
+        let (spell_book, problems) = SpellBook::load_from(&path);
+        assert_eq!(spell_book, SpellBook::default());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("Using the default spells"));
+
+        fs::remove_file(&path).unwrap();
+        assert!(SpellBook::load_from(&path).1.is_empty());
+    }
+}
@@ -1,6 +1,10 @@
-use crate::settings::Settings;
-use ggez::{glam::*, graphics::Image, Context};
-use rand::{seq::SliceRandom, Rng};
+use crate::layout::Layout;
+use crate::spell_book::SpellDefinition;
+
+use std::fmt;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 const ALACRITY_IMAGE: &[u8] = include_bytes!("../resources/alacrity.png");
 const CHAOS_METEOR_IMAGE: &[u8] = include_bytes!("../resources/chaos_meteor.png");
@@ -13,79 +17,78 @@ const ICE_WALL_IMAGE: &[u8] = include_bytes!("../resources/ice_wall.png");
 const SUN_STRIKE_IMAGE: &[u8] = include_bytes!("../resources/sun_strike.png");
 const TORNADO_IMAGE: &[u8] = include_bytes!("../resources/tornado.png");
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Position {
     pub x: f32,
     pub y: f32,
 }
 
 impl Position {
-    pub fn new(settings: &Settings) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn new(layout: &Layout, rng: &mut impl Rng) -> Self {
         let y = 0.0;
-        let x = rng.gen_range(72..(settings.window_width - 72.0) as u32) as f32;
+        // Pick a relative position so the same seed gives the same layout at any window size
+        let x = layout.spawn_x(rng.gen::<f32>());
 
         Self { x, y }
     }
 }
-#[derive(Clone, Debug)]
-pub enum SpellType {
-    Alacrity(Image),
-    ChaosMeteor(Image),
-    ColdSnap(Image),
-    DeafeningBlast(Image),
-    Emp(Image),
-    ForgeSpririt(Image),
-    GhostWalk(Image),
-    IceWall(Image),
-    SunStrike(Image),
-    Tornado(Image),
+/// Identifies a spell of the `SpellBook` in run records, statistics and replays.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct SpellType(String);
+
+impl SpellType {
+    pub fn new(id: &str) -> Self {
+        Self(id.to_string())
+    }
+
+    // Icons of the spells that come with the game
+    pub fn embedded_image(&self) -> Option<&'static [u8]> {
+        match self.0.as_str() {
+            "Alacrity" => Some(ALACRITY_IMAGE),
+            "ChaosMeteor" => Some(CHAOS_METEOR_IMAGE),
+            "ColdSnap" => Some(COLD_SNAP_IMAGE),
+            "DeafeningBlast" => Some(DEAFENING_BLAST_IMAGE),
+            "Emp" => Some(EMP_IMAGE),
+            "ForgeSpirit" => Some(FORGE_SPIRIT_IMAGE),
+            "GhostWalk" => Some(GHOST_WALK_IMAGE),
+            "IceWall" => Some(ICE_WALL_IMAGE),
+            "SunStrike" => Some(SUN_STRIKE_IMAGE),
+            "Tornado" => Some(TORNADO_IMAGE),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SpellType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Spell {
+    // Spells are numbered in the order they appear, which is also their index in the run history
+    pub id: usize,
+    pub spell_type: SpellType,
     pub cast: Vec<char>,
-    pub object: Image,
     pub position: Position,
     pub speed: f32,
 }
 
 impl Spell {
-    pub fn new(ctx: &mut Context, speed: f32, settings: &Settings) -> Self {
-        let position = Position::new(settings);
-
-        let spells = [
-            SpellType::Alacrity(Image::from_bytes(ctx, ALACRITY_IMAGE).unwrap()),
-            SpellType::ChaosMeteor(Image::from_bytes(ctx, CHAOS_METEOR_IMAGE).unwrap()),
-            SpellType::ColdSnap(Image::from_bytes(ctx, COLD_SNAP_IMAGE).unwrap()),
-            SpellType::DeafeningBlast(Image::from_bytes(ctx, DEAFENING_BLAST_IMAGE).unwrap()),
-            SpellType::Emp(Image::from_bytes(ctx, EMP_IMAGE).unwrap()),
-            SpellType::ForgeSpririt(Image::from_bytes(ctx, FORGE_SPIRIT_IMAGE).unwrap()),
-            SpellType::GhostWalk(Image::from_bytes(ctx, GHOST_WALK_IMAGE).unwrap()),
-            SpellType::IceWall(Image::from_bytes(ctx, ICE_WALL_IMAGE).unwrap()),
-            SpellType::SunStrike(Image::from_bytes(ctx, SUN_STRIKE_IMAGE).unwrap()),
-            SpellType::Tornado(Image::from_bytes(ctx, TORNADO_IMAGE).unwrap()),
-        ];
-
-        let mut rng = rand::thread_rng();
-        let spell_type = spells.choose(&mut rng).unwrap();
-
-        let (cast, object) = match &spell_type {
-            SpellType::Alacrity(image) => (vec!['E', 'W', 'W'], image),
-            SpellType::ChaosMeteor(image) => (vec!['E', 'E', 'W'], image),
-            SpellType::ColdSnap(image) => (vec!['Q', 'Q', 'Q'], image),
-            SpellType::DeafeningBlast(image) => (vec!['E', 'Q', 'W'], image),
-            SpellType::Emp(image) => (vec!['W', 'W', 'W'], image),
-            SpellType::ForgeSpririt(image) => (vec!['E', 'E', 'Q'], image),
-            SpellType::GhostWalk(image) => (vec!['Q', 'Q', 'W'], image),
-            SpellType::IceWall(image) => (vec!['E', 'Q', 'Q'], image),
-            SpellType::SunStrike(image) => (vec!['E', 'E', 'E'], image),
-            SpellType::Tornado(image) => (vec!['Q', 'W', 'W'], image),
-        };
+    pub fn new(
+        definition: &SpellDefinition,
+        speed: f32,
+        layout: &Layout,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let position = Position::new(layout, rng);
 
         Self {
-            cast,
-            object: object.clone(),
+            id: 0,
+            spell_type: definition.id.clone(),
+            cast: definition.cast(),
             position,
             speed,
         }
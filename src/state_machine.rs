@@ -1,21 +1,99 @@
 use crate::game_states::{
     game_over_pro_mode_state::GameOverProState, game_over_state::GameOverState,
-    menu_state::MenuState, play_state::MainState, pro_mode_state::ProMode,
+    game_over_time_attack_state::GameOverTimeAttackState, menu_state::MenuState,
+    notice_state::NoticeState, pause_state::PauseState, play_state::MainState,
+    replay_state::ReplayState, settings_state::SettingsState, statistics_state::StatisticsState,
+    time_attack_state::TimeAttackState,
 };
+use crate::highscores::GameMode;
+use crate::replay::Replay;
 use crate::settings::Settings;
+use crate::simulation::TimeAttackResult;
+use crate::spawner;
 
-use ggez::{event::EventHandler, glam::*, input::keyboard::KeyInput, Context, GameResult};
+use std::path::PathBuf;
+
+use ggez::{
+    event::EventHandler, glam::*, input::keyboard::KeyInput, timer::TimeContext, Context,
+    GameResult,
+};
+
+// Game logic runs in fixed steps so it behaves the same at any frame rate
+pub const TICKS_PER_SECOND: u32 = 60;
 
 pub enum Transition {
     None,
-    Menu,
-    Game,
-    ProMode,
-    GameOver { score: usize },
-    GameOverPro { score: usize, info: Option<String> },
+    // Replaces the whole stack with a new screen
+    Switch(Screen),
+    // Puts a state on top of the current one, which stays frozen underneath
+    Push(Overlay),
+    // Closes the state on top and continues the one underneath
+    Pop,
+    // Saved settings that replace the ones in use
+    ApplySettings(Box<Settings>),
+    // Without a path the most recent replay is played
+    WatchReplay { path: Option<PathBuf> },
     Quit,
 }
 
+impl Transition {
+    /// Starts a new run of `mode`, the one mapping every screen uses to start or restart a run.
+    pub fn run(mode: GameMode) -> Self {
+        Transition::Switch(Screen::run(mode, None))
+    }
+}
+
+// Every screen is built by `StateMachine::build_state`
+pub enum Screen {
+    Menu,
+    // Without a seed a random one is used, the daily challenge always has the seed of the day
+    Run {
+        mode: GameMode,
+        seed: Option<u64>,
+    },
+    // Time attack has a state and game over screen of its own
+    TimeAttack {
+        seed: Option<u64>,
+    },
+    Statistics,
+    Settings,
+    // Settings that had to be reset on start, the menu follows
+    Notice {
+        title: String,
+        notices: Vec<String>,
+    },
+    GameOver {
+        mode: GameMode,
+        score: usize,
+        speed: f32,
+    },
+    // `mode` is any of the Pro modes
+    GameOverPro {
+        mode: GameMode,
+        score: usize,
+        speed: f32,
+        info: Option<String>,
+    },
+    GameOverTimeAttack {
+        result: TimeAttackResult,
+    },
+}
+
+impl Screen {
+    /// The screen a run of `mode` is played on.
+    pub fn run(mode: GameMode, seed: Option<u64>) -> Self {
+        match mode {
+            GameMode::TimeAttack => Screen::TimeAttack { seed },
+            mode => Screen::Run { mode, seed },
+        }
+    }
+}
+
+pub enum Overlay {
+    Pause { mode: GameMode },
+    Settings,
+}
+
 pub trait GameState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<Transition>;
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()>;
@@ -23,7 +101,7 @@ pub trait GameState {
         &mut self,
         _ctx: &mut Context,
         keycode: KeyInput,
-        _repeat: bool,
+        repeat: bool,
     ) -> GameResult<Transition>;
     fn mouse_button_up_event(
         &mut self,
@@ -32,58 +110,230 @@ pub trait GameState {
         x: f32,
         y: f32,
     ) -> GameResult<Transition>;
+    // The window was resized or new settings were saved, `settings` has both
+    fn settings_changed(&mut self, ctx: &mut Context, settings: &Settings) -> GameResult;
+
+    // Events most states don't care about, they do nothing unless a state overrides them
+    fn key_up_event(&mut self, _ctx: &mut Context, _keycode: KeyInput) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn text_input_event(&mut self, _ctx: &mut Context, _character: char) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        _x: f32,
+        _y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    fn focus_event(&mut self, _ctx: &mut Context, _gained: bool) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+    // The state is thrown away, e.g. a paused run that was quit from the pause overlay
+    fn abandon(&mut self) {}
+}
+
+// Throws away the time steps that piled up, the next update starts from zero
+fn skip_pending_steps(time: &mut TimeContext) {
+    while time.check_update_time(TICKS_PER_SECOND) {}
 }
 
 pub struct StateMachine {
-    current_state: Box<dyn GameState>,
+    // Only the state on top gets updates and input, the ones underneath are frozen
+    states: Vec<Box<dyn GameState>>,
     settings: Settings,
+    // Set when a new state was installed, its first update starts from zero
+    switched: bool,
 }
 
 impl StateMachine {
-    pub fn new(initial_state: Box<dyn GameState>, settings: Settings) -> Self {
-        Self {
-            current_state: initial_state,
+    pub fn new(ctx: &mut Context, initial_screen: Screen, settings: Settings) -> GameResult<Self> {
+        let mut state_machine = Self {
+            states: Vec::new(),
             settings,
-        }
+            switched: true,
+        };
+        let initial_state = state_machine.build_state(ctx, initial_screen)?;
+        state_machine.states.push(initial_state);
+        Ok(state_machine)
+    }
+
+    fn current_state(&mut self) -> &mut Box<dyn GameState> {
+        self.states
+            .last_mut()
+            .expect("There is always a state on the stack")
     }
 
+    // Replaces the whole stack, overlays and the states they cover are left
     fn switch_state(&mut self, new_state: Box<dyn GameState>) {
-        self.current_state = new_state;
+        for state in &mut self.states {
+            state.abandon();
+        }
+        self.states = vec![new_state];
+        self.switched = true;
     }
-}
 
-impl EventHandler for StateMachine {
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
-        let transition = self.current_state.update(ctx)?;
+    fn push_overlay(&mut self, ctx: &mut Context, overlay: Overlay) -> GameResult {
+        let state: Box<dyn GameState> = match overlay {
+            Overlay::Pause { mode } => Box::new(PauseState::new(ctx, &self.settings, mode)?),
+            Overlay::Settings => Box::new(SettingsState::overlay(ctx, &self.settings)?),
+        };
+        self.states.push(state);
+        Ok(())
+    }
 
-        match transition {
-            Transition::None => Ok(()),
-            Transition::Menu => {
-                Ok(self.switch_state(Box::new(MenuState::new(ctx, &self.settings.clone())?)))
+    fn pop_state(&mut self) {
+        // The bottom state has nothing to return to
+        if self.states.len() > 1 {
+            self.states.pop();
+        }
+    }
+
+    fn apply_settings(&mut self, ctx: &mut Context, settings: Settings) -> GameResult {
+        ctx.gfx
+            .set_drawable_size(settings.window_width, settings.window_height)?;
+        self.settings = settings;
+
+        // Saved from the pause overlay, the paused run continues with the new settings
+        if self.states.len() > 1 {
+            self.pop_state();
+            for state in &mut self.states {
+                state.settings_changed(ctx, &self.settings)?;
+            }
+            return Ok(());
+        }
+
+        let menu = self.build_state(ctx, Screen::Menu)?;
+        self.switch_state(menu);
+        Ok(())
+    }
+
+    // Stays in the current state if there is no replay to watch
+    fn watch_replay(&mut self, ctx: &mut Context, path: Option<PathBuf>) -> GameResult {
+        let path = match path.or_else(Replay::latest_path) {
+            Some(path) => path,
+            None => {
+                println!("There is no replay to watch");
+                return Ok(());
             }
-            Transition::Game => {
-                Ok(self.switch_state(Box::new(MainState::new(self.settings.clone(), ctx)?)))
+        };
+
+        match Replay::load(&path) {
+            Ok(replay) => {
+                self.switch_state(Box::new(ReplayState::new(ctx, replay, &self.settings)?));
             }
-            Transition::ProMode => {
-                Ok(self.switch_state(Box::new(ProMode::new(self.settings.clone(), ctx)?)))
+            Err(e) => println!("Could not load replay {}: {}", path.display(), e),
+        }
+        Ok(())
+    }
+
+    fn handle_transition(&mut self, ctx: &mut Context, transition: Transition) -> GameResult {
+        match transition {
+            Transition::None => {}
+            Transition::Push(overlay) => self.push_overlay(ctx, overlay)?,
+            Transition::Pop => self.pop_state(),
+            Transition::ApplySettings(settings) => self.apply_settings(ctx, *settings)?,
+            Transition::WatchReplay { path } => self.watch_replay(ctx, path)?,
+            Transition::Quit => ctx.request_quit(),
+            Transition::Switch(screen) => {
+                let state = self.build_state(ctx, screen)?;
+                self.switch_state(state);
             }
+        }
 
-            Transition::GameOver { score } => Ok(self.switch_state(Box::new(GameOverState::new(
-                ctx,
+        Ok(())
+    }
+
+    // Every screen that replaces the stack is built here, a new screen only needs
+    // its variant and an arm below
+    fn build_state(&self, ctx: &mut Context, screen: Screen) -> GameResult<Box<dyn GameState>> {
+        let settings = &self.settings;
+        let state: Box<dyn GameState> = match screen {
+            Screen::Menu => Box::new(MenuState::new(ctx, settings)?),
+            Screen::TimeAttack { seed }
+            | Screen::Run {
+                mode: GameMode::TimeAttack,
+                seed,
+            } => {
+                let seed = seed.unwrap_or_else(spawner::random_seed);
+                Box::new(TimeAttackState::new(settings.clone(), ctx, seed)?)
+            }
+            Screen::Run { mode, seed } => {
+                let seed = match mode {
+                    GameMode::Daily => spawner::daily_seed(),
+                    _ => seed.unwrap_or_else(spawner::random_seed),
+                };
+                Box::new(MainState::new(settings.clone(), ctx, mode, seed)?)
+            }
+            Screen::Statistics => Box::new(StatisticsState::new(ctx, settings)?),
+            Screen::Settings => Box::new(SettingsState::new(ctx, settings)?),
+            Screen::Notice { title, notices } => {
+                Box::new(NoticeState::new(ctx, settings, &title, &notices)?)
+            }
+            Screen::GameOver { mode, score, speed } => {
+                Box::new(GameOverState::new(ctx, mode, score, speed, settings)?)
+            }
+            Screen::GameOverPro {
+                mode,
                 score,
-                &self.settings.clone(),
-            )?))),
+                speed,
+                info,
+            } => Box::new(GameOverProState::new(
+                ctx, mode, score, speed, settings, info,
+            )?),
+            Screen::GameOverTimeAttack { result } => {
+                Box::new(GameOverTimeAttackState::new(ctx, result, settings)?)
+            }
+        };
+        Ok(state)
+    }
+}
 
-            Transition::GameOverPro { score, info } => Ok(self.switch_state(Box::new(
-                GameOverProState::new(ctx, score, &self.settings.clone(), info)?,
-            ))),
+impl EventHandler for StateMachine {
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) -> GameResult {
+        // A minimized window reports a size of zero
+        if width < 1.0 || height < 1.0 {
+            return Ok(());
+        }
 
-            Transition::Quit => Ok(ctx.request_quit()),
+        // States underneath an overlay show through it, so all of them follow the window
+        self.settings = self.settings.with_window_size(width, height);
+        for state in &mut self.states {
+            state.settings_changed(ctx, &self.settings)?;
         }
+        Ok(())
     }
+
+    fn focus_event(&mut self, ctx: &mut Context, gained: bool) -> GameResult {
+        let transition = self.current_state().focus_event(ctx, gained)?;
+        self.handle_transition(ctx, transition)
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        // ggez keeps adding frame time until someone takes the steps. Frozen states and
+        // screens without a fixed time step don't, and neither does building a new state,
+        // so without this a new or resumed run would catch up on all of it at once
+        if self.states.len() > 1 || self.switched {
+            skip_pending_steps(&mut ctx.time);
+            self.switched = false;
+        }
+
+        let transition = self.current_state().update(ctx)?;
+        self.handle_transition(ctx, transition)
+    }
+
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        Ok(self.current_state.draw(ctx)?)
+        // Bottom to top, so overlays are drawn over the states they cover
+        for state in &mut self.states {
+            state.draw(ctx)?;
+        }
+        Ok(())
     }
+
     fn mouse_button_up_event(
         &mut self,
         ctx: &mut Context,
@@ -92,83 +342,71 @@ impl EventHandler for StateMachine {
         y: f32,
     ) -> GameResult {
         let transition = self
-            .current_state
+            .current_state()
             .mouse_button_up_event(ctx, button, x, y)?;
-        match transition {
-            Transition::None => {}
-            Transition::Menu => {
-                self.switch_state(Box::new(MenuState::new(ctx, &self.settings.clone())?));
-            }
-            Transition::Game => {
-                self.switch_state(Box::new(MainState::new(self.settings.clone(), ctx)?));
-            }
-            Transition::ProMode => {
-                self.switch_state(Box::new(ProMode::new(self.settings.clone(), ctx)?));
-            }
-
-            Transition::GameOver { score } => {
-                self.switch_state(Box::new(GameOverState::new(
-                    ctx,
-                    score,
-                    &self.settings.clone(),
-                )?));
-            }
+        self.handle_transition(ctx, transition)
+    }
 
-            Transition::GameOverPro { score, info } => {
-                self.switch_state(Box::new(GameOverProState::new(
-                    ctx,
-                    score,
-                    &self.settings.clone(),
-                    info,
-                )?));
-            }
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: KeyInput, repeat: bool) -> GameResult {
+        let transition = self.current_state().key_down_event(ctx, keycode, repeat)?;
+        self.handle_transition(ctx, transition)
+    }
 
-            Transition::Quit => ctx.request_quit(),
-        };
+    fn key_up_event(&mut self, ctx: &mut Context, keycode: KeyInput) -> GameResult {
+        let transition = self.current_state().key_up_event(ctx, keycode)?;
+        self.handle_transition(ctx, transition)
+    }
 
-        Ok(())
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) -> GameResult {
+        let transition = self.current_state().text_input_event(ctx, character)?;
+        self.handle_transition(ctx, transition)
     }
 
-    fn key_down_event(
+    fn mouse_motion_event(
         &mut self,
         ctx: &mut Context,
-        keycode: KeyInput,
-        _repeat: bool,
+        x: f32,
+        y: f32,
+        dx: f32,
+        dy: f32,
     ) -> GameResult {
-        let transition = self.current_state.key_down_event(ctx, keycode, _repeat)?;
-        match transition {
-            Transition::None => {}
-            Transition::Menu => {
-                self.switch_state(Box::new(MenuState::new(ctx, &self.settings.clone())?));
-            }
-            Transition::Game => {
-                self.switch_state(Box::new(MainState::new(self.settings.clone(), ctx)?));
-            }
-
-            Transition::ProMode => {
-                self.switch_state(Box::new(ProMode::new(self.settings.clone(), ctx)?));
-            }
+        let transition = self.current_state().mouse_motion_event(ctx, x, y, dx, dy)?;
+        self.handle_transition(ctx, transition)
+    }
+}
 
-            Transition::GameOver { score } => {
-                self.switch_state(Box::new(GameOverState::new(
-                    ctx,
-                    score,
-                    &self.settings.clone(),
-                )?));
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combo_book::ComboBook;
+    use crate::simulation::Simulation;
+    use crate::spawner::Spawner;
+    use crate::spell_book::SpellBook;
 
-            Transition::GameOverPro { score, info } => {
-                self.switch_state(Box::new(GameOverProState::new(
-                    ctx,
-                    score,
-                    &self.settings.clone(),
-                    info,
-                )?));
-            }
+    use std::time::Duration;
 
-            Transition::Quit => ctx.request_quit(),
-        };
+    #[test]
+    fn test_run_starts_from_zero_after_idle_time() {
+        // Time spent on a screen without a fixed time step, e.g. the menu
+        let mut time = TimeContext::new();
+        std::thread::sleep(Duration::from_millis(100));
+        time.tick();
+        skip_pending_steps(&mut time);
 
-        Ok(())
+        // The first update of the new run, the way the run states step
+        let mut simulation = Simulation::new(
+            GameMode::Normal,
+            7,
+            Spawner::uniform(),
+            SpellBook::default(),
+            ComboBook::default(),
+            1024.0,
+            1024.0,
+        );
+        let dt = Duration::from_secs_f32(1.0 / TICKS_PER_SECOND as f32);
+        while time.check_update_time(TICKS_PER_SECOND) {
+            simulation.step(dt);
+        }
+        assert_eq!(simulation.elapsed(), Duration::ZERO);
     }
 }
@@ -0,0 +1,243 @@
+use crate::run_history::RunRecord;
+use crate::spells::SpellType;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpellStats {
+    pub casts: usize,
+    pub kills: usize,
+    total_cast_time: f32,
+}
+
+impl SpellStats {
+    // Average seconds from the spell appearing to it being cast
+    pub fn average_cast_time(&self) -> Option<f32> {
+        if self.casts == 0 {
+            return None;
+        }
+        Some(self.total_cast_time / self.casts as f32)
+    }
+
+    // Share of the attempts at this spell that ended the run
+    pub fn miss_rate(&self) -> Option<f32> {
+        let attempts = self.casts + self.kills;
+        if attempts == 0 {
+            return None;
+        }
+        Some(self.kills as f32 / attempts as f32)
+    }
+}
+
+// How often going from one Pro mode spell to the next went wrong
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransitionStats {
+    pub attempts: usize,
+    pub failures: usize,
+}
+
+impl TransitionStats {
+    pub fn failure_rate(&self) -> Option<f32> {
+        if self.attempts == 0 {
+            return None;
+        }
+        Some(self.failures as f32 / self.attempts as f32)
+    }
+}
+
+/// Per spell numbers over every recorded run.
+#[derive(Debug, Clone, Default)]
+pub struct Statistics {
+    pub runs: usize,
+    spells: HashMap<SpellType, SpellStats>,
+    transitions: HashMap<(SpellType, SpellType), TransitionStats>,
+}
+
+impl Statistics {
+    pub fn from_runs(runs: &[RunRecord]) -> Self {
+        let mut spells: HashMap<SpellType, SpellStats> = HashMap::new();
+        let mut transitions: HashMap<(SpellType, SpellType), TransitionStats> = HashMap::new();
+
+        for run in runs {
+            for record in &run.spells {
+                if let Some(cast_at) = record.cast_at {
+                    let stats = spells.entry(record.spell.clone()).or_default();
+                    stats.casts += 1;
+                    stats.total_cast_time += cast_at - record.spawned_at;
+                }
+            }
+
+            let killer = run.end.killer();
+            if let Some(killer) = &killer {
+                spells.entry(killer.clone()).or_default().kills += 1;
+            }
+
+            // Pro mode spells come one after another, so every pair is a buffer transition
+            if run.mode.is_pro() {
+                for pair in run.spells.windows(2) {
+                    let (from, to) = (&pair[0], &pair[1]);
+                    let failed = to.cast_at.is_none() && killer.as_ref() == Some(&to.spell);
+                    if to.cast_at.is_none() && !failed {
+                        continue;
+                    }
+
+                    let stats = transitions
+                        .entry((from.spell.clone(), to.spell.clone()))
+                        .or_default();
+                    stats.attempts += 1;
+                    if failed {
+                        stats.failures += 1;
+                    }
+                }
+            }
+        }
+
+        Self {
+            runs: runs.len(),
+            spells,
+            transitions,
+        }
+    }
+
+    // Average cast time over all spells
+    pub fn average_cast_time(&self) -> Option<f32> {
+        let casts: usize = self.spells.values().map(|stats| stats.casts).sum();
+        if casts == 0 {
+            return None;
+        }
+        let total: f32 = self
+            .spells
+            .values()
+            .map(|stats| stats.total_cast_time)
+            .sum();
+        Some(total / casts as f32)
+    }
+
+    pub fn transition(&self, from: &SpellType, to: &SpellType) -> TransitionStats {
+        self.transitions
+            .get(&(from.clone(), to.clone()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn spell(&self, spell: &SpellType) -> SpellStats {
+        self.spells.get(spell).cloned().unwrap_or_default()
+    }
+
+    // Spell that ended the most runs, ties go to the first one by id
+    pub fn deadliest(&self) -> Option<SpellType> {
+        self.spells
+            .iter()
+            .filter(|(_, stats)| stats.kills > 0)
+            .max_by(|(a, a_stats), (b, b_stats)| a_stats.kills.cmp(&b_stats.kills).then(b.cmp(a)))
+            .map(|(spell, _)| spell.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highscores::GameMode;
+    use crate::run_history::{RunEnd, SpellRecord, RUN_FORMAT_VERSION};
+
+    fn spell(id: &str) -> SpellType {
+        SpellType::new(id)
+    }
+
+    fn record(spell: SpellType, spawned_at: f32, cast_at: Option<f32>) -> SpellRecord {
+        SpellRecord {
+            spell,
+            spawned_at,
+            cast_at,
+            keys: Vec::new(),
+        }
+    }
+
+    fn run(spells: Vec<SpellRecord>, end: RunEnd) -> RunRecord {
+        RunRecord {
+            version: RUN_FORMAT_VERSION,
+            seed: None,
+            mode: GameMode::Normal,
+            started_at: String::from("2024-01-01T00:00:00.000"),
+            score: 0,
+            duration: 10.0,
+            end,
+            spells,
+        }
+    }
+
+    #[test]
+    fn test_statistics_from_runs() {
+        let runs = vec![
+            run(
+                vec![
+                    record(spell("ForgeSpirit"), 0.0, Some(2.0)),
+                    record(spell("ChaosMeteor"), 1.0, None),
+                ],
+                RunEnd::SpellReachedBottom {
+                    spell: spell("ChaosMeteor"),
+                },
+            ),
+            run(
+                vec![record(spell("ForgeSpirit"), 0.0, Some(1.0))],
+                RunEnd::WrongSpell {
+                    cast: vec!['E', 'E', 'W'],
+                    target: Some(spell("ForgeSpirit")),
+                },
+            ),
+            run(
+                vec![record(spell("ChaosMeteor"), 0.0, None)],
+                RunEnd::SpellReachedBottom {
+                    spell: spell("ChaosMeteor"),
+                },
+            ),
+        ];
+
+        let statistics = Statistics::from_runs(&runs);
+        assert_eq!(statistics.runs, 3);
+
+        let forge_spirit = statistics.spell(&spell("ForgeSpirit"));
+        assert_eq!(forge_spirit.casts, 2);
+        assert_eq!(forge_spirit.kills, 1);
+        assert_eq!(forge_spirit.average_cast_time(), Some(1.5));
+        assert_eq!(forge_spirit.miss_rate(), Some(1.0 / 3.0));
+
+        let chaos_meteor = statistics.spell(&spell("ChaosMeteor"));
+        assert_eq!(chaos_meteor.average_cast_time(), None);
+        assert_eq!(chaos_meteor.miss_rate(), Some(1.0));
+
+        assert_eq!(statistics.spell(&spell("Emp")).miss_rate(), None);
+        assert_eq!(statistics.deadliest(), Some(spell("ChaosMeteor")));
+        assert_eq!(statistics.average_cast_time(), Some(1.5));
+    }
+
+    #[test]
+    fn test_pro_mode_transitions() {
+        let mut pro_run = run(
+            vec![
+                record(spell("Tornado"), 0.0, Some(1.0)),
+                record(spell("Emp"), 1.0, Some(2.0)),
+                record(spell("Tornado"), 2.0, Some(3.0)),
+                record(spell("Emp"), 3.0, None),
+            ],
+            RunEnd::WrongKeyPressCount {
+                spell: spell("Emp"),
+                pressed: 3,
+                required: 2,
+            },
+        );
+        pro_run.mode = GameMode::Pro;
+
+        let statistics = Statistics::from_runs(&[pro_run]);
+
+        let tornado_to_emp = statistics.transition(&spell("Tornado"), &spell("Emp"));
+        assert_eq!(tornado_to_emp.attempts, 2);
+        assert_eq!(tornado_to_emp.failure_rate(), Some(0.5));
+
+        let emp_to_tornado = statistics.transition(&spell("Emp"), &spell("Tornado"));
+        assert_eq!(emp_to_tornado.failure_rate(), Some(0.0));
+
+        let unseen = statistics.transition(&spell("Emp"), &spell("Emp"));
+        assert_eq!(unseen.failure_rate(), None);
+    }
+}